@@ -0,0 +1,106 @@
+use std::fmt::Write;
+
+const CONTEXT_LINES: usize = 3;
+
+enum Edit<'a> {
+    Keep(&'a str),
+    Remove(&'a str),
+    Add(&'a str),
+}
+
+// Longest common subsequence over lines. Env files are small enough that the
+// quadratic table is not a concern.
+fn edit_script<'a>(old_lines: &[&'a str], new_lines: &[&'a str]) -> Vec<Edit<'a>> {
+    let (n, m) = (old_lines.len(), new_lines.len());
+    let mut lengths = vec![vec![0usize; m + 1]; n + 1];
+
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = if old_lines[i] == new_lines[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut edits = vec![];
+    let (mut i, mut j) = (0, 0);
+
+    while i < n || j < m {
+        if i < n && j < m && old_lines[i] == new_lines[j] {
+            edits.push(Edit::Keep(old_lines[i]));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lengths[i + 1][j] >= lengths[i][j + 1]) {
+            edits.push(Edit::Remove(old_lines[i]));
+            i += 1;
+        } else {
+            edits.push(Edit::Add(new_lines[j]));
+            j += 1;
+        }
+    }
+
+    edits
+}
+
+/// Renders a unified diff between `old` and `new`, or an empty string when
+/// they are identical.
+pub(crate) fn unified_diff(label: &str, old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let edits = edit_script(&old_lines, &new_lines);
+
+    let changed: Vec<usize> = edits
+        .iter()
+        .enumerate()
+        .filter(|(_, edit)| !matches!(edit, Edit::Keep(_)))
+        .map(|(index, _)| index)
+        .collect();
+
+    if changed.is_empty() {
+        return String::new();
+    }
+
+    let mut diff = String::new();
+    let _ = writeln!(diff, "--- {}", label);
+    let _ = writeln!(diff, "+++ {}", label);
+
+    let mut hunk_start = 0;
+    while hunk_start < changed.len() {
+        let mut hunk_end = hunk_start;
+        while hunk_end + 1 < changed.len() && changed[hunk_end + 1] - changed[hunk_end] <= 2 * CONTEXT_LINES + 1 {
+            hunk_end += 1;
+        }
+
+        let from = changed[hunk_start].saturating_sub(CONTEXT_LINES);
+        let to = (changed[hunk_end] + CONTEXT_LINES + 1).min(edits.len());
+
+        let count = |predicate: fn(&Edit) -> bool| edits[..from].iter().filter(|edit| predicate(edit)).count();
+        let old_start = count(|edit| !matches!(edit, Edit::Add(_)));
+        let new_start = count(|edit| !matches!(edit, Edit::Remove(_)));
+        let old_len = edits[from..to].iter().filter(|edit| !matches!(edit, Edit::Add(_))).count();
+        let new_len = edits[from..to].iter().filter(|edit| !matches!(edit, Edit::Remove(_))).count();
+
+        let _ = writeln!(
+            diff,
+            "@@ -{},{} +{},{} @@",
+            old_start + usize::from(old_len > 0),
+            old_len,
+            new_start + usize::from(new_len > 0),
+            new_len
+        );
+
+        for edit in &edits[from..to] {
+            let _ = match edit {
+                Edit::Keep(line) => writeln!(diff, " {}", line),
+                Edit::Remove(line) => writeln!(diff, "-{}", line),
+                Edit::Add(line) => writeln!(diff, "+{}", line),
+            };
+        }
+
+        hunk_start = hunk_end + 1;
+    }
+
+    diff
+}
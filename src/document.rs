@@ -0,0 +1,542 @@
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use crate::parser::{self, EnvPairParseError, Segment};
+use crate::expand::{ExpandOptions, Expander};
+use crate::script::Operation;
+use crate::storage::{self, FileOptions};
+use crate::{EnvDbError, EnvPair};
+
+/// Which entry counts when a key appears more than once.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    First,
+    /// The last entry wins, as with most dotenv loaders.
+    #[default]
+    Last,
+    /// A duplicated key is an error.
+    Error,
+}
+
+/// What a logical line of an env file turned out to be.
+#[derive(Clone)]
+pub enum LineKind {
+    Entry(EnvPair),
+    Comment,
+    Blank,
+    Unparsed,
+    /// The line is not valid UTF-8. It is written back byte for byte.
+    Undecodable,
+}
+
+/// One logical line of an env file. A multiline quoted value is a single line.
+#[derive(Clone)]
+pub struct Line {
+    raw: String,
+    // The original bytes of an undecodable line; `raw` then holds a lossy
+    // rendering of them.
+    bytes: Option<Vec<u8>>,
+    kind: LineKind,
+    line_number: usize,
+    segments: Vec<Segment>,
+    key_span: Range<usize>,
+    value_span: Range<usize>,
+}
+
+impl Line {
+    fn parse(raw: &str, line_number: usize) -> Line {
+        let mut line = Line {
+            raw: raw.to_string(),
+            bytes: None,
+            kind: LineKind::Unparsed,
+            line_number,
+            segments: vec![],
+            key_span: 0..0,
+            value_span: 0..0,
+        };
+
+        line.kind = match parser::line_to_entry(parser::strip_newline(raw)) {
+            Ok(entry) => {
+                line.segments = entry.segments;
+                line.key_span = entry.key_span;
+                line.value_span = entry.value_span;
+                LineKind::Entry(entry.env_pair)
+            },
+            Err(EnvPairParseError::Comment) => LineKind::Comment,
+            Err(EnvPairParseError::Empty) => LineKind::Blank,
+            Err(EnvPairParseError::Unknown) => LineKind::Unparsed,
+        };
+
+        line
+    }
+
+    fn from_bytes(bytes: &[u8], line_number: usize) -> Line {
+        match std::str::from_utf8(bytes) {
+            Ok(raw) => Line::parse(raw, line_number),
+            Err(_) => Line {
+                raw: String::from_utf8_lossy(bytes).into_owned(),
+                bytes: Some(bytes.to_vec()),
+                kind: LineKind::Undecodable,
+                line_number,
+                segments: vec![],
+                key_span: 0..0,
+                value_span: 0..0,
+            },
+        }
+    }
+
+    // Values written by envdb are quoted so that they read back literally.
+    fn from_env_pair(env_pair: EnvPair, eol: &str, line_number: usize) -> Line {
+        Line::parse(&format!("{}{}", env_pair.to_line(), eol), line_number)
+    }
+
+    fn splice(&self, span: &Range<usize>, replacement: &str) -> Line {
+        let raw = format!("{}{}{}", &self.raw[..span.start], replacement, &self.raw[span.end..]);
+        Line::parse(&raw, self.line_number)
+    }
+
+    // Swaps the value in place, keeping the spacing around `=`, the
+    // `export` prefix and any inline comment.
+    fn with_value(&self, value: &str) -> Line {
+        self.splice(&self.value_span, &parser::quote_value(value))
+    }
+
+    fn with_key(&self, key: &str) -> Line {
+        self.splice(&self.key_span, key)
+    }
+
+    pub(crate) fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// The original text of the line, including its trailing newline if any.
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    pub fn kind(&self) -> &LineKind {
+        &self.kind
+    }
+
+    /// The 1-based number of the physical line this line starts on, as it
+    /// was when the document was parsed. Appended lines continue the count.
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    /// The line as it is written to the file.
+    pub fn raw_bytes(&self) -> &[u8] {
+        self.bytes.as_deref().unwrap_or(self.raw.as_bytes())
+    }
+
+    // The key the line assigns to, even when it is undecodable.
+    fn assigned_key(&self) -> Option<String> {
+        match &self.kind {
+            LineKind::Entry(env_pair) => Some(env_pair.key.clone()),
+            LineKind::Undecodable => {
+                let text = parser::latin1_decode(self.raw_bytes());
+                parser::assigned_key(parser::strip_newline(&text)).map(str::to_string)
+            },
+            _ => None,
+        }
+    }
+
+    /// The value of the line as bytes, undecoded: for an entry its value,
+    /// and for an undecodable assignment the unquoted and unescaped bytes.
+    pub fn raw_value(&self) -> Option<Vec<u8>> {
+        match &self.kind {
+            LineKind::Entry(env_pair) => Some(env_pair.value.clone().into_bytes()),
+            LineKind::Undecodable => {
+                let text = parser::latin1_decode(self.raw_bytes());
+                parser::line_to_env_pair(parser::strip_newline(&text))
+                    .ok()
+                    .map(|env_pair| parser::latin1_encode(&env_pair.value))
+            },
+            _ => None,
+        }
+    }
+
+    // Fails for an undecodable line, whose key is the one looked up.
+    pub(crate) fn decodable(&self, key: &str) -> Result<&Line, EnvDbError> {
+        match self.kind {
+            LineKind::Undecodable => Err(EnvDbError::Encoding {
+                key: key.to_string(),
+                line: self.line_number,
+            }),
+            _ => Ok(self),
+        }
+    }
+
+    fn push_eol(&mut self, newline: &str) {
+        self.raw.push_str(newline);
+        if let Some(bytes) = &mut self.bytes {
+            bytes.extend_from_slice(newline.as_bytes());
+        }
+    }
+
+    pub fn env_pair(&self) -> Option<&EnvPair> {
+        match &self.kind {
+            LineKind::Entry(env_pair) => Some(env_pair),
+            _ => None,
+        }
+    }
+
+    /// The assignment a comment line disables, as in `# DEBUG=true`.
+    pub fn commented_env_pair(&self) -> Option<EnvPair> {
+        match &self.kind {
+            LineKind::Comment => parser::commented_env_pair(parser::strip_newline(&self.raw)),
+            _ => None,
+        }
+    }
+
+    /// The file an include directive pulls in, as written, relative to
+    /// the including file.
+    pub fn include_path(&self) -> Option<&str> {
+        match &self.kind {
+            LineKind::Comment => parser::include_path(parser::strip_newline(&self.raw)),
+            _ => None,
+        }
+    }
+
+    fn eol(&self) -> &str {
+        &self.raw[parser::strip_newline(&self.raw).len()..]
+    }
+}
+
+/// An env file held in memory. Lines that are not touched through
+/// `set`/`append`/`remove` are written back exactly as they were read, and
+/// the file keeps its BOM, newline style and (missing) final newline.
+#[derive(Clone, Default)]
+pub struct EnvDocument {
+    lines: Vec<Line>,
+    bom: bool,
+    crlf: bool,
+    missing_final_newline: bool,
+}
+
+impl EnvDocument {
+    pub fn parse(content: &str) -> EnvDocument {
+        EnvDocument::parse_bytes(content.as_bytes())
+    }
+
+    /// Parses content that may not be valid UTF-8. Lines that are not are
+    /// kept as `LineKind::Undecodable` and written back unchanged.
+    pub fn parse_bytes(content: &[u8]) -> EnvDocument {
+        let (content, bom) = match content.strip_prefix("\u{feff}".as_bytes()) {
+            Some(content) => (content, true),
+            None => (content, false),
+        };
+        let mut line_number = 1;
+        let mut lines = vec![];
+
+        for raw in parser::split_logical_lines(content) {
+            lines.push(Line::from_bytes(raw, line_number));
+            line_number += raw.iter().filter(|&&byte| byte == b'\n').count();
+        }
+
+        let first_newline = content.iter().position(|&byte| byte == b'\n');
+
+        EnvDocument {
+            lines,
+            bom,
+            crlf: first_newline.is_some_and(|index| content[..index].ends_with(b"\r")),
+            missing_final_newline: !content.is_empty() && !content.ends_with(b"\n"),
+        }
+    }
+
+    // The line ending used for new lines, following the first line.
+    fn newline(&self) -> &'static str {
+        if self.crlf {
+            "\r\n"
+        } else {
+            "\n"
+        }
+    }
+
+    pub fn open(target_env_path: &Path) -> Result<EnvDocument, EnvDbError> {
+        match fs::read(target_env_path) {
+            Ok(content) => Ok(EnvDocument::parse_bytes(&content)),
+            Err(err) => Err(EnvDbError::Io { path: target_env_path.to_path_buf(), source: err }),
+        }
+    }
+
+    pub fn save(&self, target_env_path: &Path) -> Result<(), EnvDbError> {
+        self.save_with(target_env_path, &FileOptions::default())
+    }
+
+    /// Writes the document to `target_env_path` atomically.
+    pub fn save_with(&self, target_env_path: &Path, options: &FileOptions) -> Result<(), EnvDbError> {
+        storage::write_atomic(target_env_path, &self.to_bytes(), options)
+    }
+
+    fn next_line_number(&self) -> usize {
+        match self.lines.last() {
+            Some(line) => line.line_number + line.raw.matches('\n').count(),
+            None => 1,
+        }
+    }
+
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// Iterates over the entries in file order.
+    pub fn iter(&self) -> impl Iterator<Item = &EnvPair> {
+        self.lines.iter().filter_map(Line::env_pair)
+    }
+
+    /// The paths of the include directives, in file order.
+    pub fn includes(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().filter_map(Line::include_path)
+    }
+
+    /// Returns the entry for `key` that counts under the default
+    /// `DuplicatePolicy`, the last one. `None` as well when that entry is
+    /// not valid UTF-8; see `get_with`.
+    pub fn get(&self, key: &str) -> Option<&EnvPair> {
+        self.get_with(key, DuplicatePolicy::default()).ok().flatten()
+    }
+
+    /// The file content, with undecodable lines as they were read.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+
+        if self.bom {
+            bytes.extend_from_slice("\u{feff}".as_bytes());
+        }
+
+        for line in &self.lines {
+            bytes.extend_from_slice(line.raw_bytes());
+        }
+
+        if self.missing_final_newline {
+            if bytes.ends_with(b"\n") {
+                bytes.pop();
+            }
+            if bytes.ends_with(b"\r") {
+                bytes.pop();
+            }
+        }
+
+        bytes
+    }
+
+    // Undecodable lines count as well, so that a key is never shadowed by
+    // an entry that cannot be read.
+    fn positions(&self, key: &str) -> Vec<usize> {
+        (0..self.lines.len())
+            .filter(|&index| self.lines[index].assigned_key().is_some_and(|assigned| assigned == key))
+            .collect()
+    }
+
+    // The index of the line that holds `key` under `duplicates`.
+    fn position(&self, key: &str, duplicates: DuplicatePolicy) -> Result<Option<usize>, EnvDbError> {
+        let positions = self.positions(key);
+
+        match duplicates {
+            DuplicatePolicy::First => Ok(positions.first().copied()),
+            DuplicatePolicy::Last => Ok(positions.last().copied()),
+            DuplicatePolicy::Error if positions.len() > 1 => Err(EnvDbError::DuplicateKey {
+                key: key.to_string(),
+                lines: positions.iter().map(|&index| self.lines[index].line_number).collect(),
+            }),
+            DuplicatePolicy::Error => Ok(positions.first().copied()),
+        }
+    }
+
+    /// Returns the line holding the entry for `key` that counts under
+    /// `duplicates`. The line may be `LineKind::Undecodable`.
+    pub fn find(&self, key: &str, duplicates: DuplicatePolicy) -> Result<Option<&Line>, EnvDbError> {
+        Ok(self.position(key, duplicates)?.map(|index| &self.lines[index]))
+    }
+
+    /// Like `get`, choosing among repeated entries by `duplicates`. Fails
+    /// when the entry that counts is not valid UTF-8.
+    pub fn get_with(&self, key: &str, duplicates: DuplicatePolicy) -> Result<Option<&EnvPair>, EnvDbError> {
+        match self.find(key, duplicates)? {
+            Some(line) => Ok(line.decodable(key)?.env_pair()),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the value of the entry for `key` with `$VAR`, `${VAR}` and
+    /// `${VAR:-default}` references resolved against the other entries.
+    /// Single-quoted values and `\$` escapes are left alone. `duplicates`
+    /// picks the entry for `key` and for every referenced name.
+    pub fn expand_value(
+        &self,
+        key: &str,
+        options: &ExpandOptions,
+        duplicates: DuplicatePolicy,
+    ) -> Result<Option<String>, EnvDbError> {
+        match self.find(key, duplicates)? {
+            Some(line) => Expander::new(self, options, duplicates)
+                .expand_line(line.decodable(key)?)
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Replaces the value of the entry for `key` that counts under the
+    /// default `DuplicatePolicy`, keeping its `export` prefix, spacing and
+    /// inline comment, or appends a new entry when there is none. Returns
+    /// whether an existing entry was replaced.
+    pub fn set(&mut self, key: &str, value: &str) -> bool {
+        match self.replace(key, value, DuplicatePolicy::default()) {
+            Ok(true) => true,
+            // An undecodable entry is shadowed by the appended one.
+            Ok(false) | Err(_) => {
+                self.append(EnvPair {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                    exported: false,
+                });
+                false
+            },
+        }
+    }
+
+    /// Replaces the value of the entry for `key` that counts under
+    /// `duplicates`; other entries keep their values. Unlike `set`, nothing
+    /// is appended when there is no entry. Returns whether one was replaced.
+    pub fn replace(&mut self, key: &str, value: &str, duplicates: DuplicatePolicy) -> Result<bool, EnvDbError> {
+        match self.position(key, duplicates)? {
+            Some(index) => {
+                self.lines[index] = self.lines[index].decodable(key)?.with_value(value);
+                Ok(true)
+            },
+            None => Ok(false),
+        }
+    }
+
+    /// Removes every entry that does not count under `duplicates`, so each
+    /// key is left with a single entry. Returns the removed lines in file
+    /// order. With `DuplicatePolicy::Error` nothing is removed and the first
+    /// duplicated key is reported instead.
+    pub fn dedupe(&mut self, duplicates: DuplicatePolicy) -> Result<Vec<Line>, EnvDbError> {
+        let mut kept = vec![];
+
+        for line in &self.lines {
+            if let Some(key) = line.assigned_key() {
+                if !kept.contains(&key) {
+                    kept.push(key);
+                }
+            }
+        }
+
+        let mut dropped = vec![];
+        for key in kept {
+            let keep = self.position(&key, duplicates)?;
+            dropped.extend(self.positions(&key).into_iter().filter(|&index| Some(index) != keep));
+        }
+        dropped.sort_unstable();
+
+        let mut removed = vec![];
+        for index in dropped.into_iter().rev() {
+            removed.push(self.lines.remove(index));
+        }
+        removed.reverse();
+
+        Ok(removed)
+    }
+
+    /// Appends `env_pair` as a new line at the end of the document.
+    pub fn append(&mut self, env_pair: EnvPair) {
+        let newline = self.newline();
+
+        if let Some(last_line) = self.lines.last_mut() {
+            if last_line.eol().is_empty() {
+                last_line.push_eol(newline);
+            }
+        }
+
+        let line_number = self.next_line_number();
+        self.lines.push(Line::from_env_pair(env_pair, newline, line_number));
+    }
+
+    /// Renames every entry for `from` to `to`, keeping values and `export`
+    /// prefixes. Returns whether anything was renamed.
+    pub fn rename(&mut self, from: &str, to: &str) -> bool {
+        let mut renamed = false;
+
+        for line in self.lines.iter_mut() {
+            if line.env_pair().is_some_and(|env_pair| env_pair.key == from) {
+                *line = line.with_key(to);
+                renamed = true;
+            }
+        }
+
+        renamed
+    }
+
+    /// Runs `operations` in order. Either all of them succeed or the
+    /// document is left untouched.
+    pub fn apply(&mut self, operations: &[Operation]) -> Result<(), EnvDbError> {
+        self.apply_with(operations, DuplicatePolicy::default())
+    }
+
+    /// Like `apply`, with `duplicates` picking the entry a `set` rewrites.
+    /// `delete` and `rename` act on every entry of the key, so no shadowed
+    /// entry resurfaces; `DuplicatePolicy::Error` refuses them instead.
+    pub fn apply_with(&mut self, operations: &[Operation], duplicates: DuplicatePolicy) -> Result<(), EnvDbError> {
+        let mut staged = self.clone();
+
+        for (index, operation) in operations.iter().enumerate() {
+            let fail = |message: String| EnvDbError::Operation { number: index + 1, message };
+
+            match operation {
+                Operation::Set { key, value } => {
+                    if !staged.replace(key, value, duplicates)? {
+                        staged.append(EnvPair {
+                            key: key.to_string(),
+                            value: value.to_string(),
+                            exported: false,
+                        });
+                    }
+                },
+                Operation::Delete { key } => {
+                    staged.find(key, duplicates)?;
+                    staged.remove(key);
+                },
+                Operation::Rename { from, to } => {
+                    if staged.find(to, duplicates)?.is_some() {
+                        return Err(fail(format!("cannot rename {} to {}, the key already exists", from, to)));
+                    }
+                    staged.find(from, duplicates)?;
+                    if !staged.rename(from, to) {
+                        return Err(fail(format!("cannot rename {}, the key does not exist", from)));
+                    }
+                },
+            }
+        }
+
+        *self = staged;
+        Ok(())
+    }
+
+    /// Removes every entry for `key`, undecodable ones included. Returns
+    /// whether anything was removed.
+    pub fn remove(&mut self, key: &str) -> bool {
+        let line_count = self.lines.len();
+        self.lines.retain(|line| line.assigned_key().is_none_or(|assigned| assigned != key));
+        self.lines.len() != line_count
+    }
+}
+
+impl<'a> IntoIterator for &'a EnvDocument {
+    type Item = &'a EnvPair;
+    type IntoIter = std::iter::FilterMap<std::slice::Iter<'a, Line>, fn(&'a Line) -> Option<&'a EnvPair>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.lines.iter().filter_map(Line::env_pair)
+    }
+}
+
+// Undecodable lines are rendered lossily; use `to_bytes` to write a file.
+impl fmt::Display for EnvDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&String::from_utf8_lossy(&self.to_bytes()))
+    }
+}
@@ -0,0 +1,111 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Debug)]
+pub enum EnvDbError {
+    /// The key has no entry in the env file.
+    NotFound { key: String, path: PathBuf },
+    /// The env file could not be opened or read.
+    Io { path: PathBuf, source: io::Error },
+    /// The env file could not be rewritten.
+    Write { path: PathBuf, source: io::Error },
+    /// Another process kept the env file locked for longer than the timeout.
+    Lock { path: PathBuf, timeout: Duration },
+    /// The line holding the requested entry could not be parsed.
+    Parse { path: PathBuf, line: usize },
+    /// The entry for `key` on line `line` is not valid UTF-8.
+    Encoding { key: String, line: usize },
+    /// The key cannot be written as an env file entry.
+    InvalidKey(String),
+    /// A glob or regular expression to match entries with is malformed.
+    InvalidPattern { pattern: String, message: String },
+    /// The value of `key` refers to a variable that is not defined.
+    UndefinedVariable { key: String, name: String },
+    /// Values refer to each other in a loop; the first and last keys match.
+    ReferenceCycle(Vec<String>),
+    /// A key appears more than once while duplicates are refused. `lines`
+    /// are the 1-based line numbers of its entries.
+    DuplicateKey { key: String, lines: Vec<usize> },
+    /// Env files include each other in a loop; the first and last paths
+    /// match.
+    IncludeCycle(Vec<PathBuf>),
+    /// The key is only set in a file the env file includes, which an edit
+    /// of the env file itself cannot change.
+    IncludedKey { key: String, path: PathBuf },
+    /// An `apply` script is malformed.
+    Script { line: usize, message: String },
+    /// An operation of an `apply` script cannot be carried out. `number`
+    /// is 1-based.
+    Operation { number: usize, message: String },
+}
+
+impl fmt::Display for EnvDbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnvDbError::NotFound { path, .. } => {
+                write!(f, "Not found key in env file: {}", path.display())
+            },
+            EnvDbError::Io { path, .. } => {
+                write!(f, "Failed to open the file: {}", path.display())
+            },
+            EnvDbError::Write { path, source } => {
+                write!(f, "Failed to write the file for rewrite: {}: {}", path.display(), source)
+            },
+            EnvDbError::Lock { path, timeout } => {
+                write!(
+                    f,
+                    "Failed to lock the file within {:.1}s, another process is using it: {}",
+                    timeout.as_secs_f64(),
+                    path.display()
+                )
+            },
+            EnvDbError::Parse { path, line } => {
+                write!(f, "Failed to parse line {} in env file: {}", line, path.display())
+            },
+            EnvDbError::Encoding { key, line } => {
+                write!(f, "Failed to read the entry for {} on line {} as UTF-8", key, line)
+            },
+            EnvDbError::InvalidKey(key) => {
+                write!(f, "Invalid key: {:?}", key)
+            },
+            EnvDbError::InvalidPattern { pattern, message } => {
+                write!(f, "Invalid pattern {:?}: {}", pattern, message)
+            },
+            EnvDbError::UndefinedVariable { key, name } => {
+                write!(f, "Undefined variable {} referenced by {}", name, key)
+            },
+            EnvDbError::ReferenceCycle(keys) => {
+                write!(f, "Reference cycle between variables: {}", keys.join(" -> "))
+            },
+            EnvDbError::DuplicateKey { key, lines } => {
+                let lines: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+                write!(f, "Duplicate key {} on lines {}", key, lines.join(", "))
+            },
+            EnvDbError::IncludeCycle(paths) => {
+                let paths: Vec<String> = paths.iter().map(|path| path.display().to_string()).collect();
+                write!(f, "Include cycle between files: {}", paths.join(" -> "))
+            },
+            EnvDbError::IncludedKey { key, path } => {
+                write!(f, "{} is set in the included file {}; edit that file instead", key, path.display())
+            },
+            EnvDbError::Script { line, message } => {
+                write!(f, "Failed to parse the script at line {}: {}", line, message)
+            },
+            EnvDbError::Operation { number, message } => {
+                write!(f, "Failed to apply operation {}: {}", number, message)
+            },
+        }
+    }
+}
+
+impl Error for EnvDbError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EnvDbError::Io { source, .. } | EnvDbError::Write { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
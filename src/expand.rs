@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::env;
+
+use crate::document::{DuplicatePolicy, Line, LineKind};
+use crate::parser::Segment;
+use crate::{EnvDbError, EnvDocument};
+
+#[derive(Clone, Default)]
+pub struct ExpandOptions {
+    /// Fall back to the process environment for names the file does not
+    /// define, and for a key that refers to itself (`PATH=/opt/bin:$PATH`).
+    pub process_env: bool,
+}
+
+/// Resolves references in values against the entries of a stack of
+/// documents, later documents overriding earlier ones. Resolved values are
+/// cached, so one expander can serve many lookups.
+pub(crate) struct Expander<'a> {
+    layers: Vec<&'a EnvDocument>,
+    options: &'a ExpandOptions,
+    duplicates: DuplicatePolicy,
+    // Keyed by name and the layer the entry was found in.
+    resolved: HashMap<(String, usize), String>,
+    // Keys whose values are being expanded and their layers, outermost first.
+    stack: Vec<(String, usize)>,
+}
+
+impl<'a> Expander<'a> {
+    pub(crate) fn new(document: &'a EnvDocument, options: &'a ExpandOptions, duplicates: DuplicatePolicy) -> Expander<'a> {
+        Expander::layered(vec![document], options, duplicates)
+    }
+
+    pub(crate) fn layered(
+        layers: Vec<&'a EnvDocument>,
+        options: &'a ExpandOptions,
+        duplicates: DuplicatePolicy,
+    ) -> Expander<'a> {
+        Expander {
+            layers,
+            options,
+            duplicates,
+            resolved: HashMap::new(),
+            stack: vec![],
+        }
+    }
+
+    /// Expands a line of the topmost layer.
+    pub(crate) fn expand_line(&mut self, line: &Line) -> Result<String, EnvDbError> {
+        self.expand_layer_line(line, self.layers.len() - 1)
+    }
+
+    /// Expands a line of the layer at `layer`; a reference from a key to
+    /// itself reads the value from the layers below it.
+    pub(crate) fn expand_layer_line(&mut self, line: &Line, layer: usize) -> Result<String, EnvDbError> {
+        let key = line.env_pair().map(|env_pair| env_pair.key.clone()).unwrap_or_default();
+
+        self.stack.push((key, layer));
+        let value = self.expand_segments(line.segments());
+        self.stack.pop();
+
+        value
+    }
+
+    fn expand_segments(&mut self, segments: &[Segment]) -> Result<String, EnvDbError> {
+        let mut value = String::new();
+
+        for segment in segments {
+            match segment {
+                Segment::Text(text) => value.push_str(text),
+                Segment::Variable { name, default, .. } => match (self.lookup(name)?, default) {
+                    (Some(resolved), None) => value.push_str(&resolved),
+                    (Some(resolved), Some(_)) if !resolved.is_empty() => value.push_str(&resolved),
+                    (_, Some(default)) => value.push_str(&self.expand_segments(default)?),
+                    (None, None) => {
+                        return Err(EnvDbError::UndefinedVariable {
+                            key: self.stack.last().map(|(key, _)| key.clone()).unwrap_or_default(),
+                            name: name.clone(),
+                        })
+                    },
+                },
+            }
+        }
+
+        Ok(value)
+    }
+
+    // The topmost layer below `limit` with an entry for `name`.
+    fn find(&self, name: &str, limit: usize) -> Result<Option<(&'a Line, usize)>, EnvDbError> {
+        for layer in (0..limit).rev() {
+            if let Some(line) = self.layers[layer].find(name, self.duplicates)? {
+                return Ok(Some((line, layer)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn lookup(&mut self, name: &str) -> Result<Option<String>, EnvDbError> {
+        let self_reference = match self.stack.last() {
+            Some((key, layer)) if key == name => Some(*layer),
+            _ => None,
+        };
+
+        let found = self.find(name, self_reference.unwrap_or(self.layers.len()))?;
+
+        let (line, layer) = match found {
+            Some(found) => found,
+            None if self.options.process_env => return Ok(env::var(name).ok()),
+            None if self_reference.is_some() => {
+                return Err(EnvDbError::ReferenceCycle(vec![name.to_string(), name.to_string()]))
+            },
+            None => return Ok(None),
+        };
+
+        let cache_key = (name.to_string(), layer);
+        if let Some(value) = self.resolved.get(&cache_key) {
+            return Ok(Some(value.clone()));
+        }
+
+        if let Some(position) = self.stack.iter().position(|entry| *entry == cache_key) {
+            let mut cycle: Vec<String> = self.stack[position..].iter().map(|(key, _)| key.clone()).collect();
+            cycle.push(name.to_string());
+            return Err(EnvDbError::ReferenceCycle(cycle));
+        }
+
+        if let LineKind::Undecodable = line.kind() {
+            return Err(EnvDbError::Encoding {
+                key: name.to_string(),
+                line: line.line_number(),
+            });
+        }
+
+        let value = self.expand_layer_line(line, layer)?;
+        self.resolved.insert(cache_key, value.clone());
+        Ok(Some(value))
+    }
+}
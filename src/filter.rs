@@ -0,0 +1,84 @@
+use glob::{MatchOptions, Pattern};
+use regex::{Regex, RegexBuilder};
+
+use crate::{EnvDbError, EnvPair};
+
+/// Selects entries for `scan`, `list` and `keys`. Every condition that is
+/// set must hold; the default filter lets every entry through. Keys are
+/// matched as parsed, never against the raw line.
+#[derive(Clone, Debug, Default)]
+pub struct KeyFilter {
+    /// Only keys starting with this prefix.
+    pub prefix: Option<String>,
+    /// Only keys ending with this suffix.
+    pub suffix: Option<String>,
+    /// Only keys matching this glob, such as `*_SECRET`.
+    pub glob: Option<String>,
+    /// Only keys matching this regular expression somewhere.
+    pub regex: Option<String>,
+    /// Only entries whose value matches this regular expression somewhere.
+    pub value_match: Option<String>,
+    /// Compare keys and values without regard to case.
+    pub ignore_case: bool,
+}
+
+// A `KeyFilter` with its patterns compiled.
+pub(crate) struct KeyMatcher<'a> {
+    filter: &'a KeyFilter,
+    glob: Option<Pattern>,
+    regex: Option<Regex>,
+    value_match: Option<Regex>,
+}
+
+impl KeyFilter {
+    pub(crate) fn compile(&self) -> Result<KeyMatcher<'_>, EnvDbError> {
+        let glob = match &self.glob {
+            Some(glob) => Some(Pattern::new(glob).map_err(|err| invalid_pattern(glob, err))?),
+            None => None,
+        };
+
+        Ok(KeyMatcher {
+            filter: self,
+            glob,
+            regex: self.compile_regex(&self.regex)?,
+            value_match: self.compile_regex(&self.value_match)?,
+        })
+    }
+
+    fn compile_regex(&self, pattern: &Option<String>) -> Result<Option<Regex>, EnvDbError> {
+        match pattern {
+            Some(pattern) => RegexBuilder::new(pattern)
+                .case_insensitive(self.ignore_case)
+                .build()
+                .map(Some)
+                .map_err(|err| invalid_pattern(pattern, err)),
+            None => Ok(None),
+        }
+    }
+}
+
+fn invalid_pattern(pattern: &str, err: impl ToString) -> EnvDbError {
+    EnvDbError::InvalidPattern {
+        pattern: pattern.to_string(),
+        message: err.to_string(),
+    }
+}
+
+impl KeyMatcher<'_> {
+    pub(crate) fn matches(&self, env_pair: &EnvPair) -> bool {
+        let fold = |s: &str| if self.filter.ignore_case { s.to_lowercase() } else { s.to_string() };
+        let key = fold(&env_pair.key);
+
+        self.filter.prefix.as_ref().is_none_or(|prefix| key.starts_with(&fold(prefix)))
+            && self.filter.suffix.as_ref().is_none_or(|suffix| key.ends_with(&fold(suffix)))
+            && self.glob.as_ref().is_none_or(|glob| {
+                let options = MatchOptions {
+                    case_sensitive: !self.filter.ignore_case,
+                    ..MatchOptions::new()
+                };
+                glob.matches_with(&env_pair.key, options)
+            })
+            && self.regex.as_ref().is_none_or(|regex| regex.is_match(&env_pair.key))
+            && self.value_match.as_ref().is_none_or(|regex| regex.is_match(&env_pair.value))
+    }
+}
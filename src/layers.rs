@@ -0,0 +1,146 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::document::{DuplicatePolicy, Line};
+use crate::expand::{ExpandOptions, Expander};
+use crate::storage::{self, FileLock, FileOptions, LockMode};
+use crate::{EnvDbError, EnvDocument};
+
+struct Layer {
+    path: PathBuf,
+    // Identifies the file however its path was spelled.
+    canonical: PathBuf,
+    document: EnvDocument,
+}
+
+/// A target env file and the overlays read over it, lowest precedence
+/// first. A file is preceded by the files it includes, so that its own
+/// entries override theirs. Every file stays share-locked while this is
+/// alive.
+pub(crate) struct Layers {
+    layers: Vec<Layer>,
+    locks: Vec<FileLock>,
+}
+
+impl Layers {
+    /// Opens `target_env_path` and `overlays`, which must exist, then those
+    /// of `optional_overlays` that do.
+    pub(crate) fn open(
+        target_env_path: &Path,
+        overlays: &[PathBuf],
+        optional_overlays: &[PathBuf],
+        options: &FileOptions,
+    ) -> Result<Layers, EnvDbError> {
+        let mut layers = Layers { layers: vec![], locks: vec![] };
+
+        let optional_overlays = optional_overlays.iter().filter(|overlay| overlay.exists());
+        let overlays = overlays.iter().chain(optional_overlays).map(PathBuf::as_path);
+        for path in std::iter::once(target_env_path).chain(overlays) {
+            layers.push(path, options, &mut vec![])?;
+        }
+
+        Ok(layers)
+    }
+
+    /// Opens the files `document`, read from `path`, includes, directly or
+    /// not, but not `path` itself, which the caller has locked already.
+    pub(crate) fn included(path: &Path, document: &EnvDocument, options: &FileOptions) -> Result<Layers, EnvDbError> {
+        let mut layers = Layers { layers: vec![], locks: vec![] };
+        let mut stack = vec![(canonicalize(path)?, path.to_path_buf())];
+
+        layers.push_includes(path, document, options, &mut stack)?;
+
+        Ok(layers)
+    }
+
+    // Opens `path` after the files it includes. `stack` holds the files
+    // whose includes are being opened, outermost first.
+    fn push(&mut self, path: &Path, options: &FileOptions, stack: &mut Vec<(PathBuf, PathBuf)>) -> Result<(), EnvDbError> {
+        let canonical = canonicalize(path)?;
+
+        if let Some(position) = stack.iter().position(|(other, _)| *other == canonical) {
+            let mut cycle: Vec<PathBuf> = stack[position..].iter().map(|(_, path)| path.clone()).collect();
+            cycle.push(path.to_path_buf());
+            return Err(EnvDbError::IncludeCycle(cycle));
+        }
+
+        // A file included twice is read once, where it first appears.
+        if self.layers.iter().any(|layer| layer.canonical == canonical) {
+            return Ok(());
+        }
+
+        self.locks.push(storage::lock(path, LockMode::Shared, options)?);
+        let document = crate::open_document(path, options)?;
+
+        stack.push((canonical.clone(), path.to_path_buf()));
+        self.push_includes(path, &document, options, stack)?;
+        stack.pop();
+
+        self.layers.push(Layer {
+            path: path.to_path_buf(),
+            canonical,
+            document,
+        });
+
+        Ok(())
+    }
+
+    fn push_includes(
+        &mut self,
+        path: &Path,
+        document: &EnvDocument,
+        options: &FileOptions,
+        stack: &mut Vec<(PathBuf, PathBuf)>,
+    ) -> Result<(), EnvDbError> {
+        let parent = path.parent().unwrap_or(Path::new(""));
+
+        for include in document.includes() {
+            self.push(&parent.join(include), options, stack)?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub(crate) fn path(&self, layer: usize) -> &Path {
+        &self.layers[layer].path
+    }
+
+    pub(crate) fn document(&self, layer: usize) -> &EnvDocument {
+        &self.layers[layer].document
+    }
+
+    /// The entries for `key` that count in each layer, the effective one
+    /// first, each with the index of its layer.
+    pub(crate) fn find_all(&self, key: &str, duplicates: DuplicatePolicy) -> Result<Vec<(&Line, usize)>, EnvDbError> {
+        let mut found = vec![];
+
+        for layer in (0..self.layers.len()).rev() {
+            if let Some(line) = self.layers[layer].document.find(key, duplicates)? {
+                found.push((line, layer));
+            }
+        }
+
+        Ok(found)
+    }
+
+    /// The effective entry for `key` and the index of its layer.
+    pub(crate) fn find(&self, key: &str, duplicates: DuplicatePolicy) -> Result<Option<(&Line, usize)>, EnvDbError> {
+        Ok(self.find_all(key, duplicates)?.into_iter().next())
+    }
+
+    pub(crate) fn expander<'a>(&'a self, options: &'a ExpandOptions, duplicates: DuplicatePolicy) -> Expander<'a> {
+        let documents = self.layers.iter().map(|layer| &layer.document).collect();
+        Expander::layered(documents, options, duplicates)
+    }
+}
+
+pub(crate) fn canonicalize(path: &Path) -> Result<PathBuf, EnvDbError> {
+    fs::canonicalize(path).map_err(|err| EnvDbError::Io {
+        path: path.to_path_buf(),
+        source: err,
+    })
+}
@@ -1,240 +1,537 @@
-use std::fs;
-use std::io;
-use std::io::{BufRead, Write};
-use std::path::Path;
-
-use tempfile::NamedTempFile;
-
+use std::path::{Path, PathBuf};
+
+mod diff;
+mod document;
+mod error;
+mod expand;
+mod filter;
+mod layers;
+mod parser;
+mod script;
+mod shell;
+mod storage;
+
+pub use document::{DuplicatePolicy, EnvDocument, Line, LineKind};
+pub use error::EnvDbError;
+pub use expand::ExpandOptions;
+pub use filter::KeyFilter;
+pub use script::{parse_script, Operation};
+pub use shell::{render_exports, Shell};
+pub use storage::FileOptions;
+
+use layers::Layers;
+use storage::LockMode;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct EnvPair {
     pub key: String,
     pub value: String,
+    /// Whether the line carries a shell `export ` prefix.
+    pub exported: bool,
 }
 
 impl EnvPair {
     pub fn to_line(&self) -> String {
-        format!("{}={}", self.key, self.value)
+        let prefix = if self.exported { "export " } else { "" };
+        format!("{}{}={}", prefix, self.key, parser::quote_value(&self.value))
+    }
+}
+
+/// An entry together with where it was read from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnvEntry {
+    pub env_pair: EnvPair,
+    /// The 1-based line number the entry starts on.
+    pub line_number: usize,
+    pub path: PathBuf,
+}
+
+impl EnvEntry {
+    fn new(env_pair: EnvPair, line: &Line, path: &Path) -> EnvEntry {
+        EnvEntry {
+            env_pair,
+            line_number: line.line_number(),
+            path: path.to_path_buf(),
+        }
     }
 }
 
-enum EnvPairParseError {
-    Comment,
+#[derive(Default)]
+pub struct ReadOptions {
+    /// Resolve `${VAR}` references in values; see `EnvDocument::expand_value`.
+    pub expand: Option<ExpandOptions>,
+    /// Which entry of a repeated key is read.
+    pub duplicates: DuplicatePolicy,
+    /// Env files read over the target, later ones overriding earlier ones,
+    /// as with `.env.production` and `.env.local` over `.env`. Each must
+    /// exist.
+    pub overlays: Vec<PathBuf>,
+    /// Env files read over `overlays` in the same way, skipped when they do
+    /// not exist.
+    pub optional_overlays: Vec<PathBuf>,
+    pub file: FileOptions,
+}
+
+#[derive(Default)]
+pub struct ListOptions {
+    pub filter: KeyFilter,
+    /// Order entries by key instead of by their position in the file.
+    pub sort: bool,
+    pub read: ReadOptions,
+}
+
+#[derive(Default)]
+pub struct PutOptions {
+    /// Emit the `export ` prefix when the key is appended as a new line.
+    /// Existing lines keep whatever prefix they already had.
+    pub export: bool,
+    /// Which entry of a repeated key is rewritten.
+    pub duplicates: DuplicatePolicy,
+    /// Accept keys that are not POSIX identifiers, such as `app.name`.
+    /// Keys that cannot be read back, like ones holding `=` or a newline,
+    /// are refused regardless.
+    pub allow_any_key: bool,
+    pub file: FileOptions,
+}
+
+#[derive(Default)]
+pub struct DeleteOptions {
+    /// Every entry of a repeated key is removed either way, so that no
+    /// shadowed value resurfaces; `DuplicatePolicy::Error` refuses instead.
+    pub duplicates: DuplicatePolicy,
+    pub file: FileOptions,
+}
+
+#[derive(Default)]
+pub struct DedupeOptions {
+    /// Which entry of a repeated key is kept.
+    pub duplicates: DuplicatePolicy,
+    pub file: FileOptions,
+}
+
+#[derive(Default)]
+pub struct ApplyOptions {
+    /// Compute the result without writing it back.
+    pub dry_run: bool,
+    /// Accept keys that are not POSIX identifiers, as `PutOptions` does.
+    pub allow_any_key: bool,
+    /// Which entry of a repeated key a `set` rewrites.
+    pub duplicates: DuplicatePolicy,
+    pub file: FileOptions,
+}
+
+/// Whether a key has an entry, as reported by `exists`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyStatus {
+    Present,
+    /// The key has an entry, but its value is empty.
     Empty,
-    Unknown,
+    Missing,
+}
+
+/// Which keys a `put_many` call appended and which it overwrote, in the
+/// order they were given.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PutReport {
+    pub added: Vec<String>,
+    pub replaced: Vec<String>,
+}
+
+fn open_document(target_env_path: &Path, options: &FileOptions) -> Result<EnvDocument, EnvDbError> {
+    let document = EnvDocument::open(target_env_path)?;
+    let warn = options.warn.unwrap_or(|message| eprintln!("{}", message));
+
+    for line in document.lines() {
+        match line.kind() {
+            LineKind::Unparsed => warn(&format!("Skip parse line: {}", parser::strip_newline(line.raw()))),
+            LineKind::Undecodable => warn(&format!("Skip undecodable line: {}", line.line_number())),
+            _ => {},
+        }
+    }
+
+    Ok(document)
 }
 
-fn line_to_env_pair(line: &str) -> Result<EnvPair, EnvPairParseError> {
-    if let Some((key, value)) = line.split_once('=') {
-        Ok(EnvPair {
+pub fn get(target_env_path: &Path, key: &str, options: &ReadOptions) -> Result<EnvEntry, EnvDbError> {
+    let layers = Layers::open(target_env_path, &options.overlays, &options.optional_overlays, &options.file)?;
+
+    if let Some((line, layer)) = layers.find(key, options.duplicates)? {
+        let line = line.decodable(key)?;
+        let mut env_pair = line.env_pair().cloned().expect("a decodable match is an entry");
+        if let Some(expand_options) = &options.expand {
+            env_pair.value = layers.expander(expand_options, options.duplicates).expand_layer_line(line, layer)?;
+        }
+        return Ok(EnvEntry::new(env_pair, line, layers.path(layer)));
+    }
+
+    for layer in 0..layers.len() {
+        let unparsed_line = layers.document(layer).lines().iter().find(|line| {
+            matches!(line.kind(), LineKind::Unparsed) && parser::assigned_key(line.raw()) == Some(key)
+        });
+
+        if let Some(line) = unparsed_line {
+            return Err(EnvDbError::Parse {
+                path: layers.path(layer).to_path_buf(),
+                line: line.line_number(),
+            });
+        }
+    }
+
+    Err(EnvDbError::NotFound {
+        key: key.to_string(),
+        path: target_env_path.to_path_buf(),
+    })
+}
+
+/// Every entry for `key` across the target and its overlays: the effective
+/// one first, then those it shadows. Only the effective value is expanded.
+pub fn explain(target_env_path: &Path, key: &str, options: &ReadOptions) -> Result<Vec<EnvEntry>, EnvDbError> {
+    let effective = get(target_env_path, key, options)?;
+    let layers = Layers::open(target_env_path, &options.overlays, &options.optional_overlays, &options.file)?;
+    let mut entries = vec![effective];
+
+    for (line, layer) in layers.find_all(key, options.duplicates)?.into_iter().skip(1) {
+        if let Some(env_pair) = line.env_pair() {
+            entries.push(EnvEntry::new(env_pair.clone(), line, layers.path(layer)));
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Returns the value of `key` as bytes, without requiring it to be valid
+/// UTF-8. The value is unquoted and unescaped but never expanded.
+pub fn get_raw(target_env_path: &Path, key: &str, options: &ReadOptions) -> Result<Vec<u8>, EnvDbError> {
+    let layers = Layers::open(target_env_path, &options.overlays, &options.optional_overlays, &options.file)?;
+
+    match layers.find(key, options.duplicates)?.and_then(|(line, _)| line.raw_value()) {
+        Some(value) => Ok(value),
+        None => Err(EnvDbError::NotFound {
             key: key.to_string(),
-            value: value.to_string()
-        })
-    } else if line.is_empty() {
-        Err(EnvPairParseError::Empty)
-    } else if line.starts_with('#') {
-        Err(EnvPairParseError::Comment)
-    } else {
-        Err(EnvPairParseError::Unknown)
-    }
-}
-
-pub fn get(target_env_path: &Path, key: &str) -> Result<EnvPair, String> {
-    let key_with_equal = format!("{}=", key);
-    let target_env_path_str = target_env_path.to_str().expect("Fail to convert env path to string");
-
-    if let Ok(file) = fs::File::open(target_env_path) {
-        let reader = io::BufReader::new(file);
-
-        for line in reader.lines() {
-            if let Ok(line) = line {
-                if line.starts_with(&key_with_equal) {
-                    let env_pair = line_to_env_pair(&line);
-
-                    match env_pair {
-                        Ok(env_pair) => {
-                            return Ok(env_pair)
-                        },
-                        Err(EnvPairParseError::Comment) => {
-                            continue
-                        },
-                        Err(EnvPairParseError::Empty) => {
-                            continue
-                        },
-                        Err(EnvPairParseError::Unknown) => {
-                            eprintln!("Skip parse line: {}", line)
-                        },
+            path: target_env_path.to_path_buf(),
+        }),
+    }
+}
+
+// The effective entries that pass `filter`. Each is listed where it stands
+// in the layer that supplies it, layer by layer.
+fn read_entries(target_env_path: &Path, filter: &KeyFilter, options: &ReadOptions) -> Result<Vec<EnvEntry>, EnvDbError> {
+    let matcher = filter.compile()?;
+    let layers = Layers::open(target_env_path, &options.overlays, &options.optional_overlays, &options.file)?;
+    let default_expand_options = ExpandOptions::default();
+    let expand_options = options.expand.as_ref().unwrap_or(&default_expand_options);
+    let mut expander = layers.expander(expand_options, options.duplicates);
+    let mut entries = vec![];
+
+    for layer in 0..layers.len() {
+        for line in layers.document(layer).lines() {
+            if let Some(env_pair) = line.env_pair() {
+                if matcher.matches(env_pair) {
+                    // Entries shadowed by another entry for the same key are skipped.
+                    let effective = layers.find(&env_pair.key, options.duplicates)?;
+                    if !effective.is_some_and(|(effective, _)| std::ptr::eq(effective, line)) {
+                        continue;
+                    }
+
+                    let mut env_pair = env_pair.clone();
+                    if options.expand.is_some() {
+                        env_pair.value = expander.expand_layer_line(line, layer)?;
                     }
+                    entries.push(EnvEntry::new(env_pair, line, layers.path(layer)));
                 }
-            } else {
-                return Err(format!("Failed to read a line in env file: {}", target_env_path_str));
             }
         }
-        Err(format!("Not found key in env file: {}", target_env_path_str))
-    } else {
-        Err(format!("Failed to open the file: {}", target_env_path_str))
     }
+
+    Ok(entries)
 }
 
-pub fn scan(target_env_path: &Path, key_prefix: &str) -> Result<Vec<EnvPair>, String> {
-    let target_env_path_str = target_env_path.to_str().expect("Fail to convert env path to string");
+/// Looks up each of `keys` without decoding or expanding its value.
+pub fn exists(target_env_path: &Path, keys: &[String], options: &ReadOptions) -> Result<Vec<KeyStatus>, EnvDbError> {
+    let layers = Layers::open(target_env_path, &options.overlays, &options.optional_overlays, &options.file)?;
+    let mut statuses = vec![];
+
+    for key in keys {
+        let status = match layers.find(key, options.duplicates)?.and_then(|(line, _)| line.raw_value()) {
+            Some(value) if value.is_empty() => KeyStatus::Empty,
+            Some(_) => KeyStatus::Present,
+            None => KeyStatus::Missing,
+        };
+        statuses.push(status);
+    }
 
-    if let Ok(file) = fs::File::open(target_env_path) {
-        let reader = io::BufReader::new(file);
+    Ok(statuses)
+}
 
-        let mut matched_pairs = vec![];
+/// The effective entries of `paths`, read in order with later files
+/// overriding earlier ones, each recording the file that supplied it. Every
+/// path must exist; `options.overlays` and `options.optional_overlays` are
+/// read after them.
+pub fn load_layers(paths: &[PathBuf], options: &ReadOptions) -> Result<Vec<EnvEntry>, EnvDbError> {
+    let Some((target_env_path, overlays)) = paths.split_first() else {
+        return Ok(vec![]);
+    };
+    let options = ReadOptions {
+        expand: options.expand.clone(),
+        duplicates: options.duplicates,
+        overlays: overlays.iter().chain(&options.overlays).cloned().collect(),
+        optional_overlays: options.optional_overlays.clone(),
+        file: options.file.clone(),
+    };
+
+    read_entries(target_env_path, &KeyFilter::default(), &options)
+}
 
-        for line in reader.lines() {
-            if let Ok(line) = line {
-                if line.starts_with(&key_prefix) {
-                    let env_pair = line_to_env_pair(&line);
+/// The file writes go to when no layer is picked: the highest-precedence
+/// one of `optional_overlays` that exists, else the last of `overlays`,
+/// each of which must exist, else the target.
+pub fn write_layer(
+    target_env_path: &Path,
+    overlays: &[PathBuf],
+    optional_overlays: &[PathBuf],
+) -> Result<PathBuf, EnvDbError> {
+    for overlay in overlays {
+        std::fs::metadata(overlay).map_err(|err| EnvDbError::Io { path: overlay.clone(), source: err })?;
+    }
 
-                    match env_pair {
-                        Ok(env_pair) => {
-                            matched_pairs.push(env_pair)
-                        },
-                        Err(EnvPairParseError::Comment) => {
-                            continue
-                        },
-                        Err(EnvPairParseError::Empty) => {
-                            continue
-                        },
-                        Err(EnvPairParseError::Unknown) => {
-                            eprintln!("Skip parse line: {}", line)
-                        },
-                    }
+    Ok(optional_overlays
+        .iter()
+        .rev()
+        .find(|overlay| overlay.exists())
+        .or(overlays.last())
+        .map_or_else(|| target_env_path.to_path_buf(), PathBuf::clone))
+}
+
+pub fn scan(target_env_path: &Path, filter: &KeyFilter, options: &ReadOptions) -> Result<Vec<EnvEntry>, EnvDbError> {
+    read_entries(target_env_path, filter, options)
+}
+
+/// The entries that pass `options.filter`, in file order unless sorted.
+pub fn list(target_env_path: &Path, options: &ListOptions) -> Result<Vec<EnvEntry>, EnvDbError> {
+    let mut entries = read_entries(target_env_path, &options.filter, &options.read)?;
+
+    if options.sort {
+        entries.sort_by(|a, b| a.env_pair.key.cmp(&b.env_pair.key));
+    }
+
+    Ok(entries)
+}
+
+/// The keys that `list` would return, without their values.
+pub fn keys(target_env_path: &Path, options: &ListOptions) -> Result<Vec<String>, EnvDbError> {
+    let entries = list(target_env_path, options)?;
+    Ok(entries.into_iter().map(|entry| entry.env_pair.key).collect())
+}
+
+/// Assignments that are commented out (`# KEY=value`) and pass `filter`,
+/// read from the same layers as `scan`, layer by layer in file order.
+/// Skipped lines are not reported; `scan` reports them already.
+pub fn scan_commented(target_env_path: &Path, filter: &KeyFilter, options: &ReadOptions) -> Result<Vec<EnvEntry>, EnvDbError> {
+    let matcher = filter.compile()?;
+    let file_options = FileOptions {
+        warn: Some(|_| {}),
+        ..options.file.clone()
+    };
+    let layers = Layers::open(target_env_path, &options.overlays, &options.optional_overlays, &file_options)?;
+    let mut entries = vec![];
+
+    for layer in 0..layers.len() {
+        for line in layers.document(layer).lines() {
+            if let Some(env_pair) = line.commented_env_pair() {
+                if matcher.matches(&env_pair) {
+                    entries.push(EnvEntry::new(env_pair, line, layers.path(layer)));
                 }
-            } else {
-                return Err(format!("Failed to read a line in env file: {}", target_env_path_str));
             }
         }
+    }
+
+    Ok(entries)
+}
 
-        Ok(matched_pairs)
+// Refuses keys that are not POSIX identifiers or, with `allow_any_key`,
+// that would not read back as themselves.
+fn validate_key(key: &str, allow_any_key: bool) -> Result<(), EnvDbError> {
+    let valid = if allow_any_key {
+        parser::is_writable_key(key)
     } else {
-        Err(format!("Failed to open the file: {}", target_env_path_str))
-    }
-}
-
-pub fn put(target_env_path: &Path, key: &str, new_value: &str) -> Result<(), String> {
-    let target_env_path_str = target_env_path.to_str().expect("Fail to convert env path to string");
-
-    if let Ok(file) = fs::File::open(target_env_path) {
-        let reader = io::BufReader::new(file);
-        let mut to_be_replaced = false;
-
-        let mut new_lines = vec![];
-
-        for line in reader.lines() {
-            if let Ok(line) = line {
-                let env_pair = line_to_env_pair(&line);
-
-                match env_pair {
-                    Ok(mut env_pair) => {
-                        if env_pair.key == key {
-                            to_be_replaced = true;
-                            env_pair.value = new_value.to_string();
-                            new_lines.push(env_pair.to_line())
-                        } else {
-                            new_lines.push(line)
-                        }
-                    },
-                    Err(EnvPairParseError::Comment) => {
-                        new_lines.push(line);
-                    },
-                    Err(EnvPairParseError::Empty) => {
-                        new_lines.push(line);
-                    },
-                    Err(EnvPairParseError::Unknown) => {
-                        eprintln!("Skip parse line: {}", line);
-                        new_lines.push(line)
-                    },
-                }
-            } else {
-                return Err(format!("Failed to read a line in env file: {}", target_env_path_str));
-            }
-        }
+        parser::is_identifier(key)
+    };
 
-        if !to_be_replaced {
-            let new_pair = EnvPair { key: key.to_string(), value: new_value.to_string() };
-            new_lines.push(new_pair.to_line());
-        }
+    if valid {
+        Ok(())
+    } else {
+        Err(EnvDbError::InvalidKey(key.to_string()))
+    }
+}
 
-        if let Ok(mut tmpfile) = NamedTempFile::new() {
-            for new_line in new_lines {
-                let res = writeln!(tmpfile, "{}", new_line);
+// Refuses to edit `key` in `document` when only a file it includes sets it.
+fn check_included(
+    document: &EnvDocument,
+    included: &Layers,
+    key: &str,
+    duplicates: DuplicatePolicy,
+) -> Result<(), EnvDbError> {
+    if document.find(key, duplicates)?.is_some() {
+        return Ok(());
+    }
 
-                if let Err(err) = res {
-                    return Err(format!("Failed to write the file for rewrite: {}", err));
-                }
-            }
+    match included.find(key, duplicates)? {
+        Some((_, layer)) => Err(EnvDbError::IncludedKey {
+            key: key.to_string(),
+            path: included.path(layer).to_path_buf(),
+        }),
+        None => Ok(()),
+    }
+}
+
+pub fn put(target_env_path: &Path, key: &str, new_value: &str, options: &PutOptions) -> Result<(), EnvDbError> {
+    let env_pair = EnvPair {
+        key: key.to_string(),
+        value: new_value.to_string(),
+        exported: options.export,
+    };
 
-            let tmpfile_path = tmpfile.into_temp_path();
+    put_many(target_env_path, &[env_pair], options).map(|_| ())
+}
+
+/// Applies every assignment in a single rewrite of the file. Later
+/// assignments to the same key win. Nothing is written when any key is
+/// invalid. The `exported` flag of an assignment is
+/// only used when its key is newly added.
+pub fn put_many(target_env_path: &Path, env_pairs: &[EnvPair], options: &PutOptions) -> Result<PutReport, EnvDbError> {
+    for env_pair in env_pairs {
+        validate_key(&env_pair.key, options.allow_any_key)?;
+    }
+
+    let _lock = storage::lock(target_env_path, LockMode::Exclusive, &options.file)?;
+    let mut document = open_document(target_env_path, &options.file)?;
+    let included = Layers::included(target_env_path, &document, &options.file)?;
+    let mut report = PutReport::default();
 
-            if let Err(err) = fs::copy(tmpfile_path, target_env_path) {
-                return Err(format!("Failed to write the file for rewrite: {}", err));
+    for env_pair in env_pairs {
+        check_included(&document, &included, &env_pair.key, options.duplicates)?;
+        let known = report.added.contains(&env_pair.key) || report.replaced.contains(&env_pair.key);
+
+        if document.replace(&env_pair.key, &env_pair.value, options.duplicates)? {
+            if !known {
+                report.replaced.push(env_pair.key.clone());
             }
         } else {
-            return Err("Failed to open the tempfile for rewrite".to_string());
+            document.append(EnvPair {
+                exported: env_pair.exported || options.export,
+                ..env_pair.clone()
+            });
+            report.added.push(env_pair.key.clone());
         }
+    }
 
-        Ok(())
-    } else {
-        Err(format!("Failed to open the file: {}", target_env_path_str))
-    }
-}
-
-pub fn delete(target_env_path: &std::path::PathBuf, key: &str) -> Result<(), String> {
-    let target_env_path_str = target_env_path.to_str().expect("Fail to convert env path to string");
-
-    if let Ok(file) = fs::File::open(target_env_path) {
-        let reader = io::BufReader::new(file);
-
-        let mut new_lines = vec![];
-
-        for line in reader.lines() {
-            if let Ok(line) = line {
-                let env_pair = line_to_env_pair(&line);
-
-                match env_pair {
-                    Ok(env_pair) => {
-                        if env_pair.key != key {
-                            new_lines.push(line)
-                        }
-                    },
-                    Err(EnvPairParseError::Comment) => {
-                        new_lines.push(line);
-                    },
-                    Err(EnvPairParseError::Empty) => {
-                        new_lines.push(line);
-                    },
-                    Err(EnvPairParseError::Unknown) => {
-                        eprintln!("Skip parse line: {}", line);
-                        new_lines.push(line)
-                    },
-                }
-            } else {
-                return Err(format!("Failed to read a line in env file: {}", target_env_path_str));
-            }
+    document.save_with(target_env_path, &options.file)?;
+
+    Ok(report)
+}
+
+pub fn delete(target_env_path: &Path, key: &str, options: &DeleteOptions) -> Result<(), EnvDbError> {
+    let _lock = storage::lock(target_env_path, LockMode::Exclusive, &options.file)?;
+    let mut document = open_document(target_env_path, &options.file)?;
+    let included = Layers::included(target_env_path, &document, &options.file)?;
+    check_included(&document, &included, key, options.duplicates)?;
+    document.remove(key);
+    document.save_with(target_env_path, &options.file)
+}
+
+/// Removes `key` from every file of `paths` that defines it and returns
+/// those files. Every path must exist; paths naming a file already listed
+/// are skipped. Every file
+/// is locked and checked before the first is rewritten, but each is
+/// rewritten on its own.
+pub fn delete_layers(paths: &[PathBuf], key: &str, options: &DeleteOptions) -> Result<Vec<PathBuf>, EnvDbError> {
+    let mut locks = vec![];
+    let mut documents = vec![];
+    let mut seen = vec![];
+
+    for path in paths {
+        // Locking a file twice, under another spelling or through a
+        // symlink, would wait on our own lock.
+        let canonical = layers::canonicalize(path)?;
+        if seen.contains(&canonical) {
+            continue;
+        }
+        seen.push(canonical);
+        locks.push(storage::lock(path, LockMode::Exclusive, &options.file)?);
+        let document = open_document(path, &options.file)?;
+        if document.find(key, options.duplicates)?.is_some() {
+            documents.push((path, document));
         }
+    }
 
-        if let Ok(mut tmpfile) = NamedTempFile::new() {
-            for new_line in new_lines {
-                let res = writeln!(tmpfile, "{}", new_line);
+    if documents.is_empty() {
+        // Nothing gets written, so the files can be read again unlocked.
+        drop(locks);
+        for path in paths {
+            let document = open_document(path, &options.file)?;
+            let included = Layers::included(path, &document, &options.file)?;
+            check_included(&document, &included, key, options.duplicates)?;
+        }
+        return Ok(vec![]);
+    }
 
-                if let Err(err) = res {
-                    return Err(format!("Failed to write the file for rewrite: {}", err));
-                }
-            }
+    let mut deleted = vec![];
+    for (path, mut document) in documents {
+        document.remove(key);
+        document.save_with(path, &options.file)?;
+        deleted.push(path.clone());
+    }
 
-            let tmpfile_path = tmpfile.into_temp_path();
+    Ok(deleted)
+}
 
-            if let Err(err) = fs::copy(tmpfile_path, target_env_path) {
-                return Err(format!("Failed to write the file for rewrite: {}", err));
-            }
-        } else {
-            return Err("Failed to open the tempfile for rewrite".to_string());
+/// Leaves a single entry per key, chosen by `options.duplicates`, and
+/// returns the lines that were dropped. The file is only rewritten when
+/// something was dropped.
+pub fn dedupe(target_env_path: &Path, options: &DedupeOptions) -> Result<Vec<Line>, EnvDbError> {
+    let _lock = storage::lock(target_env_path, LockMode::Exclusive, &options.file)?;
+    let mut document = open_document(target_env_path, &options.file)?;
+    let dropped = document.dedupe(options.duplicates)?;
+
+    if !dropped.is_empty() {
+        document.save_with(target_env_path, &options.file)?;
+    }
+
+    Ok(dropped)
+}
+
+/// Runs `operations` against one in-memory copy of the file and writes the
+/// result back in a single atomic rewrite, so either every operation lands
+/// or none does. Nothing is written when a key to set or rename to is
+/// invalid. Returns a unified diff of the change.
+pub fn apply(target_env_path: &Path, operations: &[Operation], options: &ApplyOptions) -> Result<String, EnvDbError> {
+    for operation in operations {
+        match operation {
+            Operation::Set { key, .. } | Operation::Rename { to: key, .. } => validate_key(key, options.allow_any_key)?,
+            Operation::Delete { .. } => {},
         }
+    }
 
-        Ok(())
-    } else {
-        Err(format!("Failed to open the file: {}", target_env_path_str))
+    let lock_mode = if options.dry_run { LockMode::Shared } else { LockMode::Exclusive };
+    let _lock = storage::lock(target_env_path, lock_mode, &options.file)?;
+    let mut document = open_document(target_env_path, &options.file)?;
+    let included = Layers::included(target_env_path, &document, &options.file)?;
+    for operation in operations {
+        match operation {
+            Operation::Set { key, .. } | Operation::Delete { key } | Operation::Rename { from: key, .. } => {
+                check_included(&document, &included, key, options.duplicates)?
+            },
+        }
     }
+
+    let original = document.to_string();
+    let label = target_env_path.display().to_string();
+
+    document.apply_with(operations, options.duplicates)?;
+
+    if !options.dry_run {
+        document.save_with(target_env_path, &options.file)?;
+    }
+
+    Ok(diff::unified_diff(&label, &original, &document.to_string()))
 }
@@ -1,132 +1,966 @@
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::fmt::Display;
 use std::process::exit;
+use std::sync::OnceLock;
+use std::time::Duration;
 
-use exitcode;
-use clap::Arg;
+use clap::{Arg, ArgGroup, ArgMatches};
+use serde_json::{json, Value};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Plain,
+    Json,
+    Jsonl,
+}
+
+static OUTPUT_FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+fn output_format() -> OutputFormat {
+    OUTPUT_FORMAT.get().copied().unwrap_or(OutputFormat::Plain)
+}
+
+// The exit status of `exists` for a key that is set to an empty value,
+// apart from the 2 clap exits with on bad arguments.
+const EMPTY_VALUE: i32 = 11;
+
+fn exit_code(err: &envdb::EnvDbError) -> i32 {
+    match err {
+        envdb::EnvDbError::NotFound { .. } => 1,
+        envdb::EnvDbError::DuplicateKey { .. } => 3,
+        envdb::EnvDbError::IncludedKey { .. } => 4,
+        envdb::EnvDbError::UndefinedVariable { .. } => 5,
+        envdb::EnvDbError::ReferenceCycle(_) => 6,
+        envdb::EnvDbError::IncludeCycle(_) => 7,
+        envdb::EnvDbError::Encoding { .. } => 8,
+        envdb::EnvDbError::Operation { .. } => 9,
+        envdb::EnvDbError::Script { .. } => 10,
+        envdb::EnvDbError::InvalidKey(_) => 12,
+        envdb::EnvDbError::InvalidPattern { .. } => exitcode::USAGE,
+        envdb::EnvDbError::Parse { .. } => exitcode::DATAERR,
+        envdb::EnvDbError::Io { .. } => exitcode::NOINPUT,
+        envdb::EnvDbError::Write { .. } => exitcode::CANTCREAT,
+        envdb::EnvDbError::Lock { .. } => exitcode::TEMPFAIL,
+    }
+}
+
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| format!("invalid number of seconds: {}", value))
+}
+
+fn target_env_arg() -> Arg {
+    Arg::new("target-env")
+        .long("target-env")
+        .value_name("PATH")
+        .value_parser(clap::value_parser!(std::path::PathBuf))
+        .default_value(".env")
+}
+
+// The target of a read command, with any overlays read over it.
+fn layered_target_env_arg() -> Arg {
+    target_env_arg()
+        .help("Env file to read; repeat to read later files over earlier ones")
+        .action(clap::ArgAction::Append)
+}
+
+fn mode_arg() -> Arg {
+    Arg::new("mode")
+        .long("mode")
+        .value_name("MODE")
+        .help("Also read `<target>.<MODE>` and then `<target>.local` over the target, when they exist")
+}
+
+// The files named after the first `--target-env`, which must exist.
+fn overlays(matches: &ArgMatches) -> Vec<PathBuf> {
+    matches.get_many::<PathBuf>("target-env").unwrap_or_default().skip(1).cloned().collect()
+}
+
+// The files `--mode` adds, read only when they exist.
+fn mode_overlays(matches: &ArgMatches) -> Vec<PathBuf> {
+    let (Some(target_env_path), Some(mode)) = (
+        matches.get_one::<PathBuf>("target-env"),
+        matches.try_get_one::<String>("mode").ok().flatten(),
+    ) else {
+        return vec![];
+    };
+
+    [mode.as_str(), "local"]
+        .into_iter()
+        .map(|suffix| {
+            let mut path = target_env_path.clone().into_os_string();
+            path.push(".");
+            path.push(suffix);
+            PathBuf::from(path)
+        })
+        .collect()
+}
+
+fn allow_any_key_arg() -> Arg {
+    Arg::new("allow-any-key")
+        .long("allow-any-key")
+        .help("Accept keys that are not POSIX identifiers, such as `app.name`")
+        .action(clap::ArgAction::SetTrue)
+}
+
+fn write_layer_arg() -> Arg {
+    Arg::new("write-layer")
+        .long("write-layer")
+        .value_name("PATH")
+        .help("Layer to modify; defaults to the highest-precedence one that exists")
+        .value_parser(clap::value_parser!(PathBuf))
+}
+
+// Whether two paths name the same file, however they are spelled.
+fn same_file(path: &Path, other: &Path) -> bool {
+    path == other
+        || matches!(
+            (std::fs::canonicalize(path), std::fs::canonicalize(other)),
+            (Ok(path), Ok(other)) if path == other
+        )
+}
+
+// The file a write command modifies, picked among its layers.
+fn write_layer(matches: &ArgMatches) -> PathBuf {
+    let target_env_path = matches.get_one::<PathBuf>("target-env").unwrap();
+    let overlays = overlays(matches);
+    let mode_overlays = mode_overlays(matches);
+
+    match matches.get_one::<PathBuf>("write-layer") {
+        Some(layer)
+            if std::iter::once(target_env_path)
+                .chain(&overlays)
+                .chain(&mode_overlays)
+                .any(|path| same_file(path, layer)) =>
+        {
+            layer.clone()
+        },
+        Some(layer) => fail_with(
+            format!("Not a layer of this command: {}", layer.display()),
+            exitcode::USAGE,
+        ),
+        None => match envdb::write_layer(target_env_path, &overlays, &mode_overlays) {
+            Ok(layer) => layer,
+            Err(err) => fail(err),
+        },
+    }
+}
+
+fn lock_timeout_arg() -> Arg {
+    Arg::new("lock-timeout")
+        .long("lock-timeout")
+        .value_name("SECONDS")
+        .help("How long to wait for another envdb process to release the file")
+        .value_parser(parse_seconds)
+        .default_value("10")
+}
+
+fn follow_symlinks_arg() -> Arg {
+    Arg::new("follow-symlinks")
+        .long("follow-symlinks")
+        .help("Rewrite the file a symlinked target points to instead of replacing the link")
+        .action(clap::ArgAction::SetTrue)
+}
+
+fn file_options(matches: &ArgMatches) -> envdb::FileOptions {
+    let follow_symlinks = matches
+        .try_get_one::<bool>("follow-symlinks")
+        .ok()
+        .flatten()
+        .copied()
+        .unwrap_or(false);
+
+    envdb::FileOptions {
+        follow_symlinks,
+        lock_timeout: matches.get_one::<Duration>("lock-timeout").copied(),
+        warn: Some(warn),
+    }
+}
+
+fn duplicates_arg() -> Arg {
+    Arg::new("duplicates")
+        .long("duplicates")
+        .value_name("POLICY")
+        .help("Which entry of a repeated key counts: the first, the last, or none (an error)")
+        .value_parser(["first", "last", "error"])
+        .default_value("last")
+}
+
+fn duplicate_policy(matches: &ArgMatches) -> envdb::DuplicatePolicy {
+    match matches.get_one::<String>("duplicates").map(String::as_str) {
+        Some("first") => envdb::DuplicatePolicy::First,
+        Some("error") => envdb::DuplicatePolicy::Error,
+        _ => envdb::DuplicatePolicy::Last,
+    }
+}
+
+fn sort_arg() -> Arg {
+    Arg::new("sort")
+        .long("sort")
+        .help("Order entries by key instead of by their position in the file")
+        .action(clap::ArgAction::SetTrue)
+}
+
+fn filter_args() -> [Arg; 6] {
+    [
+        Arg::new("key_prefix").help("Only keys starting with this prefix"),
+        Arg::new("suffix")
+            .long("suffix")
+            .value_name("SUFFIX")
+            .help("Only keys ending with this suffix"),
+        Arg::new("glob")
+            .long("glob")
+            .value_name("PATTERN")
+            .help("Only keys matching this glob, such as `*_SECRET`"),
+        Arg::new("regex")
+            .long("regex")
+            .value_name("PATTERN")
+            .help("Only keys matching this regular expression"),
+        Arg::new("value-match")
+            .long("value-match")
+            .value_name("PATTERN")
+            .help("Only entries whose value matches this regular expression"),
+        Arg::new("ignore-case")
+            .long("ignore-case")
+            .help("Match keys and values case-insensitively")
+            .action(clap::ArgAction::SetTrue),
+    ]
+}
+
+fn key_filter(matches: &ArgMatches) -> envdb::KeyFilter {
+    envdb::KeyFilter {
+        prefix: matches.get_one::<String>("key_prefix").cloned(),
+        suffix: matches.get_one::<String>("suffix").cloned(),
+        glob: matches.get_one::<String>("glob").cloned(),
+        regex: matches.get_one::<String>("regex").cloned(),
+        value_match: matches.get_one::<String>("value-match").cloned(),
+        ignore_case: matches.get_flag("ignore-case"),
+    }
+}
+
+fn list_options(matches: &ArgMatches) -> envdb::ListOptions {
+    envdb::ListOptions {
+        filter: key_filter(matches),
+        sort: matches.get_flag("sort"),
+        read: read_options(matches),
+    }
+}
+
+fn expand_args() -> [Arg; 2] {
+    [
+        Arg::new("expand")
+            .long("expand")
+            .help("Resolve $VAR, ${VAR} and ${VAR:-default} references against other keys")
+            .action(clap::ArgAction::SetTrue),
+        Arg::new("expand-env")
+            .long("expand-env")
+            .help("Like --expand, falling back to the process environment")
+            .action(clap::ArgAction::SetTrue),
+    ]
+}
+
+fn read_options(matches: &ArgMatches) -> envdb::ReadOptions {
+    let flag = |id: &str| matches.try_get_one::<bool>(id).ok().flatten().copied().unwrap_or(false);
+    let expand = if flag("expand-env") {
+        Some(envdb::ExpandOptions { process_env: true })
+    } else if flag("expand") {
+        Some(envdb::ExpandOptions { process_env: false })
+    } else {
+        None
+    };
+
+    envdb::ReadOptions {
+        expand,
+        duplicates: duplicate_policy(matches),
+        overlays: overlays(matches),
+        optional_overlays: mode_overlays(matches),
+        file: file_options(matches),
+    }
+}
+
+// Reports a skipped line on stderr, as a JSON object when JSON output is
+// selected.
+fn warn(message: &str) {
+    match output_format() {
+        OutputFormat::Plain => eprintln!("{}", message),
+        OutputFormat::Json | OutputFormat::Jsonl => eprintln!("{}", json!({ "warning": message })),
+    }
+}
+
+// Reports an error on stderr, as a JSON object when JSON output is selected.
+fn fail_with(message: impl Display, code: i32) -> ! {
+    match output_format() {
+        OutputFormat::Plain => eprintln!("{}", message),
+        OutputFormat::Json | OutputFormat::Jsonl => {
+            eprintln!("{}", json!({ "error": message.to_string(), "code": code }))
+        },
+    }
+    exit(code);
+}
+
+fn fail(err: envdb::EnvDbError) -> ! {
+    fail_with(&err, exit_code(&err));
+}
+
+fn entry_json(entry: &envdb::EnvEntry) -> Value {
+    json!({
+        "key": entry.env_pair.key,
+        "value": entry.env_pair.value,
+        "line": entry.line_number,
+        "file": entry.path.display().to_string(),
+    })
+}
+
+// Prints records as one JSON array, or one object per line for `jsonl`.
+fn print_records(records: Vec<Value>) {
+    if output_format() == OutputFormat::Jsonl {
+        for record in records {
+            println!("{}", record);
+        }
+    } else {
+        println!("{}", Value::Array(records));
+    }
+}
+
+fn parse_assignment(assignment: &str) -> Option<envdb::EnvPair> {
+    assignment.split_once('=').map(|(key, value)| envdb::EnvPair {
+        key: key.to_string(),
+        value: value.to_string(),
+        exported: false,
+    })
+}
+
+// Collects assignments from `--from-file`, `--stdin` and the command line,
+// in that order, so the command line has the last word.
+fn put_assignments(matches: &ArgMatches) -> Vec<envdb::EnvPair> {
+    let mut env_pairs = vec![];
+
+    if let Some(source_path) = matches.get_one::<PathBuf>("from-file") {
+        match envdb::EnvDocument::open(source_path) {
+            Ok(document) => env_pairs.extend(document.iter().cloned()),
+            Err(err) => fail(err),
+        }
+    }
+
+    if matches.get_flag("stdin") {
+        let mut content = String::new();
+        if let Err(err) = std::io::stdin().read_to_string(&mut content) {
+            fail_with(format!("Failed to read assignments from stdin: {}", err), exitcode::IOERR);
+        }
+        env_pairs.extend(envdb::EnvDocument::parse(&content).iter().cloned());
+    }
+
+    let assignments: Vec<&String> = matches.get_many::<String>("assignments").unwrap_or_default().collect();
+
+    if let [key, value] = assignments[..] {
+        if !key.contains('=') {
+            env_pairs.push(envdb::EnvPair {
+                key: key.to_string(),
+                value: value.to_string(),
+                exported: false,
+            });
+            return env_pairs;
+        }
+    }
+
+    for assignment in assignments {
+        match parse_assignment(assignment) {
+            Some(env_pair) => env_pairs.push(env_pair),
+            None => fail_with(format!("Invalid assignment, expected KEY=VALUE: {}", assignment), exitcode::USAGE),
+        }
+    }
+
+    env_pairs
+}
+
+fn read_script(script_path: &str) -> String {
+    let content = if script_path == "-" {
+        let mut content = String::new();
+        std::io::stdin().read_to_string(&mut content).map(|_| content)
+    } else {
+        std::fs::read_to_string(script_path)
+    };
+
+    match content {
+        Ok(content) => content,
+        Err(err) => fail_with(format!("Failed to read the script: {}: {}", script_path, err), exitcode::NOINPUT),
+    }
+}
+
+// Replaces this process with the command, so that it receives signals sent
+// to envdb and its exit status becomes envdb's. Only returns on failure.
+#[cfg(unix)]
+fn exec(command: &mut std::process::Command) -> std::io::Error {
+    use std::os::unix::process::CommandExt;
+
+    command.exec()
+}
+
+#[cfg(not(unix))]
+fn exec(command: &mut std::process::Command) -> std::io::Error {
+    match command.status() {
+        Ok(status) => exit(status.code().unwrap_or(1)),
+        Err(err) => err,
+    }
+}
+
+fn run(matches: &ArgMatches) -> ! {
+    let target_env_path = matches.get_one::<PathBuf>("target-env").unwrap();
+    let options = envdb::ListOptions {
+        read: read_options(matches),
+        ..Default::default()
+    };
+    let entries = match envdb::list(target_env_path, &options) {
+        Ok(entries) => entries,
+        Err(err) => fail(err),
+    };
+
+    let mut args = matches.get_many::<String>("command").unwrap_or_default();
+    let program = args.next().unwrap();
+    let mut command = std::process::Command::new(program);
+    command.args(args);
+    if matches.get_flag("clean") {
+        command.env_clear();
+    }
+    command.envs(entries.into_iter().map(|entry| (entry.env_pair.key, entry.env_pair.value)));
+
+    let err = exec(&mut command);
+    // The exit statuses a shell uses for commands it cannot find or run.
+    let code = match err.kind() {
+        std::io::ErrorKind::NotFound => 127,
+        _ => 126,
+    };
+    fail_with(format!("Failed to run {}: {}", program, err), code);
+}
 
 fn main() {
     let cmd = clap::Command::new("envdb")
         .bin_name("envdb")
         .subcommand_required(true)
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .value_name("FORMAT")
+                .help("Print results as plain text, a JSON document or JSON lines")
+                .value_parser(["plain", "json", "jsonl"])
+                .default_value("plain")
+                .global(true)
+        )
         .subcommand(
             clap::command!("get")
+                .arg(layered_target_env_arg())
+                .arg(mode_arg())
+                .arg(lock_timeout_arg())
+                .arg(duplicates_arg())
+                .args(expand_args())
                 .arg(
-                    Arg::new("target-env")
-                        .long("target-env")
-                        .value_name("PATH")
-                        .value_parser(clap::value_parser!(std::path::PathBuf))
-                        .default_value(".env"),
+                    Arg::new("raw")
+                        .long("raw")
+                        .help("Print the value bytes as stored, even when they are not valid UTF-8")
+                        .conflicts_with_all(["expand", "expand-env"])
+                        .action(clap::ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("explain")
+                        .long("explain")
+                        .help("List every file that sets the key, the one supplying the value first")
+                        .conflicts_with("raw")
+                        .action(clap::ArgAction::SetTrue)
                 )
                 .arg(
                     Arg::new("key")
                         .required(true)
                 )
         )
+        .subcommand(
+            clap::command!("exists")
+                .arg(layered_target_env_arg())
+                .arg(mode_arg())
+                .arg(lock_timeout_arg())
+                .arg(duplicates_arg())
+                .arg(
+                    Arg::new("all")
+                        .long("all")
+                        .help("Succeed only when every key is set (the default)")
+                        .action(clap::ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("any")
+                        .long("any")
+                        .help("Succeed when at least one key is set")
+                        .conflicts_with("all")
+                        .action(clap::ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("keys")
+                        .value_name("KEY")
+                        .num_args(1..)
+                        .required(true)
+                )
+        )
         .subcommand(
             clap::command!("put")
+                .arg(layered_target_env_arg())
+                .arg(mode_arg())
+                .arg(write_layer_arg())
+                .arg(lock_timeout_arg())
+                .arg(duplicates_arg())
+                .arg(follow_symlinks_arg())
+                .arg(
+                    Arg::new("assignments")
+                        .value_name("KEY=VALUE")
+                        .help("Assignments to apply; `put <key> <value>` is accepted as well")
+                        .num_args(1..)
+                )
                 .arg(
-                    Arg::new("target-env")
-                        .long("target-env")
+                    Arg::new("from-file")
+                        .long("from-file")
                         .value_name("PATH")
+                        .help("Read assignments from another env file")
                         .value_parser(clap::value_parser!(std::path::PathBuf))
-                        .default_value(".env")
                 )
                 .arg(
-                    Arg::new("key").required(true)
+                    Arg::new("stdin")
+                        .long("stdin")
+                        .help("Read assignments in env file syntax from stdin")
+                        .action(clap::ArgAction::SetTrue)
+                )
+                .group(
+                    ArgGroup::new("input")
+                        .args(["assignments", "from-file", "stdin"])
+                        .multiple(true)
+                        .required(true)
                 )
                 .arg(
-                    Arg::new("value").required(true)
+                    Arg::new("export")
+                        .long("export")
+                        .help("Prefix the line with `export ` when the key is newly added")
+                        .action(clap::ArgAction::SetTrue)
                 )
+                .arg(allow_any_key_arg())
         )
         .subcommand(
             clap::command!("scan")
+                .arg(layered_target_env_arg())
+                .arg(mode_arg())
+                .arg(lock_timeout_arg())
+                .arg(duplicates_arg())
+                .args(expand_args())
                 .arg(
-                    Arg::new("target-env")
-                        .long("target-env")
-                        .value_name("PATH")
-                        .value_parser(clap::value_parser!(std::path::PathBuf))
-                        .default_value(".env")
+                    Arg::new("include-commented")
+                        .long("include-commented")
+                        .help("Also list commented-out assignments, after the active ones, as `# KEY=VALUE`")
+                        .action(clap::ArgAction::SetTrue)
                 )
-                .arg(
-                    Arg::new("key_prefix").required(true)
+                .args(filter_args())
+                .group(
+                    ArgGroup::new("filter")
+                        .args(["key_prefix", "suffix", "glob", "regex", "value-match"])
+                        .multiple(true)
+                        .required(true)
                 )
         )
+        .subcommand(
+            clap::command!("list")
+                .arg(layered_target_env_arg())
+                .arg(mode_arg())
+                .arg(lock_timeout_arg())
+                .arg(duplicates_arg())
+                .args(expand_args())
+                .arg(sort_arg())
+                .args(filter_args())
+        )
+        .subcommand(
+            clap::command!("keys")
+                .arg(layered_target_env_arg())
+                .arg(mode_arg())
+                .arg(lock_timeout_arg())
+                .arg(duplicates_arg())
+                .arg(sort_arg())
+                .args(filter_args())
+        )
         .subcommand(
             clap::command!("delete")
-            .arg(
-                Arg::new("target-env")
-                .long("target-env")
-                .value_name("PATH")
-                .value_parser(clap::value_parser!(std::path::PathBuf))
-                .default_value(".env")
+                .arg(layered_target_env_arg())
+                .arg(mode_arg())
+                .arg(write_layer_arg())
+                .arg(lock_timeout_arg())
+                .arg(duplicates_arg())
+                .arg(follow_symlinks_arg())
+                .arg(
+                    Arg::new("all-layers")
+                        .long("all-layers")
+                        .help("Remove the key from every layer that sets it")
+                        .conflicts_with("write-layer")
+                        .action(clap::ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("key").required(true)
+                )
+        )
+        .subcommand(
+            clap::command!("dedupe")
+                .arg(target_env_arg())
+                .arg(lock_timeout_arg())
+                .arg(duplicates_arg())
+                .arg(follow_symlinks_arg())
+        )
+        .subcommand(
+            clap::command!("export")
+                .arg(layered_target_env_arg())
+                .arg(mode_arg())
+                .arg(lock_timeout_arg())
+                .arg(duplicates_arg())
+                .args(expand_args())
+                .arg(
+                    Arg::new("shell")
+                        .long("shell")
+                        .value_name("SHELL")
+                        .help("Shell to print the statements for")
+                        .value_parser(["bash", "zsh", "sh", "fish", "powershell", "nushell"])
+                        .default_value("bash")
+                )
+                .arg(sort_arg())
+                .args(filter_args())
+        )
+        .subcommand(
+            clap::command!("run")
+                .arg(layered_target_env_arg())
+                .arg(mode_arg())
+                .arg(lock_timeout_arg())
+                .arg(duplicates_arg())
+                .args(expand_args())
+                .arg(
+                    Arg::new("clean")
+                        .long("clean")
+                        .help("Start from an empty environment instead of the inherited one")
+                        .action(clap::ArgAction::SetTrue)
                 )
-            .arg(
-                Arg::new("key").required(true)
+                .arg(
+                    Arg::new("command")
+                        .value_name("COMMAND")
+                        .num_args(1..)
+                        .trailing_var_arg(true)
+                        .allow_hyphen_values(true)
+                        .required(true)
+                )
+        )
+        .subcommand(
+            clap::command!("apply")
+                .arg(target_env_arg())
+                .arg(lock_timeout_arg())
+                .arg(duplicates_arg())
+                .arg(follow_symlinks_arg())
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Print the resulting diff without writing the file")
+                        .action(clap::ArgAction::SetTrue)
+                )
+                .arg(allow_any_key_arg())
+                .arg(
+                    Arg::new("script")
+                        .value_name("SCRIPT")
+                        .help("Path to the operation script, or `-` to read it from stdin")
+                        .required(true)
                 )
         );
 
     let matches = cmd.get_matches();
+    let output_format = match matches.get_one::<String>("output").map(String::as_str) {
+        Some("json") => OutputFormat::Json,
+        Some("jsonl") => OutputFormat::Jsonl,
+        _ => OutputFormat::Plain,
+    };
+    OUTPUT_FORMAT.get_or_init(|| output_format);
+
     match matches.subcommand() {
         Some(("get", matches)) => {
             let target_env_path = matches.get_one::<std::path::PathBuf>("target-env").unwrap();
             let key = matches.get_one::<String>("key").unwrap();
-            match envdb::get(target_env_path, key) {
-                Ok(env_pair) => {
-                    println!("{}", env_pair.value);
+            if matches.get_flag("raw") {
+                match envdb::get_raw(target_env_path, key, &read_options(matches)) {
+                    Ok(value) => {
+                        let mut stdout = std::io::stdout();
+                        if stdout.write_all(&value).and_then(|_| stdout.write_all(b"\n")).is_err() {
+                            exit(exitcode::IOERR);
+                        }
+                        exit(exitcode::OK);
+                    },
+                    Err(err) => fail(err),
+                }
+            }
+            if matches.get_flag("explain") {
+                match envdb::explain(target_env_path, key, &read_options(matches)) {
+                    Ok(entries) => {
+                        if output_format != OutputFormat::Plain {
+                            let records = entries.iter().enumerate().map(|(index, entry)| {
+                                let mut record = entry_json(entry);
+                                record["shadowed"] = json!(index > 0);
+                                record
+                            });
+                            print_records(records.collect());
+                        } else {
+                            for (index, entry) in entries.iter().enumerate() {
+                                let shadowed = if index > 0 { " (shadowed)" } else { "" };
+                                println!(
+                                    "{}:{} {}{}",
+                                    entry.path.display(),
+                                    entry.line_number,
+                                    entry.env_pair.to_line(),
+                                    shadowed
+                                );
+                            }
+                        }
+                        exit(exitcode::OK);
+                    },
+                    Err(err) => fail(err),
+                }
+            }
+            match envdb::get(target_env_path, key, &read_options(matches)) {
+                Ok(entry) => {
+                    match output_format {
+                        OutputFormat::Plain => println!("{}", entry.env_pair.value),
+                        OutputFormat::Json | OutputFormat::Jsonl => println!("{}", entry_json(&entry)),
+                    }
                     exit(exitcode::OK);
                 },
-                Err(err_msg) => {
-                    eprintln!("{}", err_msg);
-                    exit(1); // XXX: could be better?
-                }
+                Err(err) => fail(err),
             }
         },
-        Some(("put", matches)) => {
+        Some(("exists", matches)) => {
             let target_env_path = matches.get_one::<std::path::PathBuf>("target-env").unwrap();
-            let key = matches.get_one::<String>("key").unwrap();
-            let value = matches.get_one::<String>("value").unwrap();
-            match envdb::put(target_env_path, key, value) {
-                Ok(_) => {
+            let keys: Vec<String> = matches.get_many::<String>("keys").unwrap_or_default().cloned().collect();
+            let mut options = read_options(matches);
+            // `exists` only reports through its exit status.
+            options.file.warn = Some(|_| {});
+            match envdb::exists(target_env_path, &keys, &options) {
+                Ok(statuses) => {
+                    let count = |status| statuses.iter().filter(|&&other| other == status).count();
+                    let present = count(envdb::KeyStatus::Present);
+                    let missing = count(envdb::KeyStatus::Missing);
+                    let code = if matches.get_flag("any") {
+                        match (present, missing) {
+                            (1.., _) => exitcode::OK,
+                            (0, missing) if missing == statuses.len() => 1,
+                            _ => EMPTY_VALUE,
+                        }
+                    } else {
+                        match (present, missing) {
+                            (_, 1..) => 1,
+                            (present, 0) if present == statuses.len() => exitcode::OK,
+                            _ => EMPTY_VALUE,
+                        }
+                    };
+                    exit(code);
+                },
+                Err(err) => fail(err),
+            }
+        },
+        Some(("put", matches)) => {
+            let target_env_path = &write_layer(matches);
+            let env_pairs = put_assignments(matches);
+            let options = envdb::PutOptions {
+                export: matches.get_flag("export"),
+                duplicates: duplicate_policy(matches),
+                allow_any_key: matches.get_flag("allow-any-key"),
+                file: file_options(matches),
+            };
+            match envdb::put_many(target_env_path, &env_pairs, &options) {
+                Ok(report) => {
+                    if output_format == OutputFormat::Plain {
+                        for key in report.added {
+                            println!("added {}", key);
+                        }
+                        for key in report.replaced {
+                            println!("replaced {}", key);
+                        }
+                    } else {
+                        let added = report.added.iter().map(|key| json!({ "key": key, "action": "added" }));
+                        let replaced = report.replaced.iter().map(|key| json!({ "key": key, "action": "replaced" }));
+                        print_records(added.chain(replaced).collect());
+                    }
                     exit(exitcode::OK);
                 },
-                Err(err_msg) => {
-                    eprintln!("{}", err_msg);
-                    exit(1); // XXX: could be better?
-                }
+                Err(err) => fail(err),
             }
         },
         Some(("scan", matches)) => {
             let target_env_path = matches.get_one::<std::path::PathBuf>("target-env").unwrap();
-            let key_prefix = matches.get_one::<String>("key_prefix").unwrap();
-            match envdb::scan(target_env_path, key_prefix) {
-                Ok(env_pairs) => {
-                    if env_pairs.is_empty() {
+            let filter = key_filter(matches);
+            let options = read_options(matches);
+            let commented_entries = if matches.get_flag("include-commented") {
+                match envdb::scan_commented(target_env_path, &filter, &options) {
+                    Ok(entries) => entries,
+                    Err(err) => fail(err),
+                }
+            } else {
+                vec![]
+            };
+            match envdb::scan(target_env_path, &filter, &options) {
+                Ok(entries) => {
+                    if output_format != OutputFormat::Plain {
+                        let mut records: Vec<Value> = entries.iter().map(entry_json).collect();
+                        records.extend(commented_entries.iter().map(|entry| {
+                            let mut record = entry_json(entry);
+                            record["commented"] = json!(true);
+                            record
+                        }));
+                        print_records(records);
+                    } else {
+                        for entry in &entries {
+                            println!("{}", entry.env_pair.to_line());
+                        }
+                        for entry in &commented_entries {
+                            println!("# {}", entry.env_pair.to_line());
+                        }
+                    }
+
+                    if entries.is_empty() && commented_entries.is_empty() {
                         exit(1);
+                    }
+                    exit(exitcode::OK);
+                },
+                Err(err) => fail(err),
+            }
+        },
+        Some(("list", matches)) => {
+            let target_env_path = matches.get_one::<std::path::PathBuf>("target-env").unwrap();
+            match envdb::list(target_env_path, &list_options(matches)) {
+                Ok(entries) => {
+                    if output_format != OutputFormat::Plain {
+                        print_records(entries.iter().map(entry_json).collect());
                     } else {
-                        for env_pair in env_pairs {
-                            println!("{}", env_pair.to_line());
+                        for entry in entries {
+                            println!("{}", entry.env_pair.to_line());
                         }
-                        exit(exitcode::OK);
                     }
+                    exit(exitcode::OK);
                 },
-                Err(err_msg) => {
-                    eprintln!("{}", err_msg);
-                    exit(1); // XXX: could be better?
-                }
+                Err(err) => fail(err),
             }
         },
-        Some(("delete", matches)) => {
+        Some(("keys", matches)) => {
             let target_env_path = matches.get_one::<std::path::PathBuf>("target-env").unwrap();
+            match envdb::keys(target_env_path, &list_options(matches)) {
+                Ok(keys) => {
+                    if output_format != OutputFormat::Plain {
+                        print_records(keys.into_iter().map(Value::String).collect());
+                    } else {
+                        for key in keys {
+                            println!("{}", key);
+                        }
+                    }
+                    exit(exitcode::OK);
+                },
+                Err(err) => fail(err),
+            }
+        },
+        Some(("delete", matches)) => {
             let key = matches.get_one::<String>("key").unwrap();
-            match envdb::delete(target_env_path, key) {
+            let options = envdb::DeleteOptions {
+                duplicates: duplicate_policy(matches),
+                file: file_options(matches),
+            };
+            if matches.get_flag("all-layers") {
+                let mut paths = vec![matches.get_one::<PathBuf>("target-env").unwrap().clone()];
+                paths.extend(overlays(matches));
+                paths.extend(mode_overlays(matches).into_iter().filter(|path| path.exists()));
+                match envdb::delete_layers(&paths, key, &options) {
+                    Ok(deleted) => {
+                        if output_format == OutputFormat::Plain {
+                            for path in deleted {
+                                println!("deleted from {}", path.display());
+                            }
+                        } else {
+                            print_records(deleted.iter().map(|path| json!({ "file": path.display().to_string() })).collect());
+                        }
+                        exit(exitcode::OK);
+                    },
+                    Err(err) => fail(err),
+                }
+            }
+            let target_env_path = &write_layer(matches);
+            match envdb::delete(target_env_path, key, &options) {
                 Ok(_) => {
                     exit(exitcode::OK);
                 },
-                Err(err_msg) => {
-                    eprintln!("{}", err_msg);
-                    exit(1); // XXX: could be better?
-                }
+                Err(err) => fail(err),
+            }
+        },
+        Some(("dedupe", matches)) => {
+            let target_env_path = matches.get_one::<std::path::PathBuf>("target-env").unwrap();
+            let options = envdb::DedupeOptions {
+                duplicates: duplicate_policy(matches),
+                file: file_options(matches),
+            };
+            match envdb::dedupe(target_env_path, &options) {
+                Ok(dropped) => {
+                    let dropped = dropped
+                        .iter()
+                        .map(|line| (line.line_number(), line.raw().trim_end_matches(['\r', '\n'])));
+                    if output_format == OutputFormat::Plain {
+                        for (line_number, text) in dropped {
+                            println!("dropped line {}: {}", line_number, text);
+                        }
+                    } else {
+                        print_records(dropped.map(|(line_number, text)| json!({ "line": line_number, "text": text })).collect());
+                    }
+                    exit(exitcode::OK);
+                },
+                Err(err) => fail(err),
+            }
+        },
+        Some(("apply", matches)) => {
+            let target_env_path = matches.get_one::<std::path::PathBuf>("target-env").unwrap();
+            let script_path = matches.get_one::<String>("script").unwrap();
+            let operations = match envdb::parse_script(&read_script(script_path)) {
+                Ok(operations) => operations,
+                Err(err) => fail(err),
+            };
+            let options = envdb::ApplyOptions {
+                dry_run: matches.get_flag("dry-run"),
+                allow_any_key: matches.get_flag("allow-any-key"),
+                duplicates: duplicate_policy(matches),
+                file: file_options(matches),
+            };
+            match envdb::apply(target_env_path, &operations, &options) {
+                Ok(diff) => {
+                    match output_format {
+                        OutputFormat::Plain if options.dry_run => print!("{}", diff),
+                        OutputFormat::Plain => {},
+                        OutputFormat::Json | OutputFormat::Jsonl => println!("{}", json!({ "diff": diff })),
+                    }
+                    exit(exitcode::OK);
+                },
+                Err(err) => fail(err),
+            }
+        },
+        Some(("export", matches)) => {
+            let target_env_path = matches.get_one::<PathBuf>("target-env").unwrap();
+            let shell = match matches.get_one::<String>("shell").map(String::as_str) {
+                Some("fish") => envdb::Shell::Fish,
+                Some("powershell") => envdb::Shell::PowerShell,
+                Some("nushell") => envdb::Shell::Nushell,
+                _ => envdb::Shell::Posix,
+            };
+            match envdb::list(target_env_path, &list_options(matches)) {
+                Ok(entries) => {
+                    for entry in entries.iter().filter(|entry| !shell.accepts_key(&entry.env_pair.key)) {
+                        warn(&format!("Skip key the shell cannot name: {}", entry.env_pair.key));
+                    }
+                    let env_pairs: Vec<envdb::EnvPair> = entries.into_iter().map(|entry| entry.env_pair).collect();
+                    print!("{}", envdb::render_exports(shell, &env_pairs));
+                    exit(exitcode::OK);
+                },
+                Err(err) => fail(err),
             }
         },
+        Some(("run", matches)) => run(matches),
         _ => unreachable!("clap should ensure we don't get here"),
     }
 }
@@ -0,0 +1,438 @@
+use std::ops::Range;
+
+use crate::EnvPair;
+
+fn is_safe_unquoted(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || "_-.,/:@%+=^~".contains(ch)
+}
+
+// Picks the least surprising quoting so the line stays loadable by
+// docker-compose, dotenv libraries and shells alike.
+pub(crate) fn quote_value(value: &str) -> String {
+    if value.chars().all(is_safe_unquoted) {
+        value.to_string()
+    } else if !value.contains(['\'', '\n', '\r']) {
+        format!("'{}'", value)
+    } else {
+        let mut quoted = String::with_capacity(value.len() + 2);
+        quoted.push('"');
+        for ch in value.chars() {
+            match ch {
+                '"' => quoted.push_str("\\\""),
+                '\\' => quoted.push_str("\\\\"),
+                '$' => quoted.push_str("\\$"),
+                '`' => quoted.push_str("\\`"),
+                '\n' => quoted.push_str("\\n"),
+                '\r' => quoted.push_str("\\r"),
+                '\t' => quoted.push_str("\\t"),
+                _ => quoted.push(ch),
+            }
+        }
+        quoted.push('"');
+        quoted
+    }
+}
+
+/// A piece of a parsed value: literal text, or a `$VAR` / `${VAR}` /
+/// `${VAR:-default}` reference that `--expand` resolves.
+#[derive(Clone, Debug)]
+pub(crate) enum Segment {
+    Text(String),
+    Variable {
+        name: String,
+        default: Option<Vec<Segment>>,
+        /// The reference as written, used when the value is read literally.
+        source: String,
+    },
+}
+
+fn is_name_start(ch: char) -> bool {
+    ch.is_ascii_alphabetic() || ch == '_'
+}
+
+fn is_name_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '_'
+}
+
+// Scans a reference right after its `$`. Returns the variable name, the raw
+// default text and the length consumed, or `None` if this is a plain `$`.
+fn scan_variable(s: &str) -> Option<(&str, Option<&str>, usize)> {
+    if let Some(body) = s.strip_prefix('{') {
+        let mut depth = 0;
+        let mut end = None;
+        let mut prev = '\0';
+        for (index, ch) in body.char_indices() {
+            match ch {
+                '{' if prev == '$' => depth += 1,
+                '}' if depth == 0 => {
+                    end = Some(index);
+                    break;
+                },
+                '}' => depth -= 1,
+                _ => {},
+            }
+            prev = ch;
+        }
+
+        let body = &body[..end?];
+        let name_len = body.find(|ch| !is_name_char(ch)).unwrap_or(body.len());
+        let name = &body[..name_len];
+        if !name.starts_with(is_name_start) {
+            return None;
+        }
+
+        match &body[name_len..] {
+            "" => Some((name, None, body.len() + 2)),
+            rest => rest.strip_prefix(":-").map(|default| (name, Some(default), body.len() + 2)),
+        }
+    } else {
+        let name_len = s.find(|ch| !is_name_char(ch)).unwrap_or(s.len());
+        let name = &s[..name_len];
+        if name.starts_with(is_name_start) {
+            Some((name, None, name_len))
+        } else {
+            None
+        }
+    }
+}
+
+// Splits a value into text and references. With `escapes`, backslash
+// escapes are decoded as in double-quoted values.
+fn scan_segments(s: &str, escapes: bool) -> Vec<Segment> {
+    let mut segments = vec![];
+    let mut text = String::new();
+    let mut rest = s;
+
+    while let Some(ch) = rest.chars().next() {
+        rest = &rest[ch.len_utf8()..];
+
+        match ch {
+            '\\' if escapes => match rest.chars().next() {
+                Some(escaped) => {
+                    rest = &rest[escaped.len_utf8()..];
+                    match escaped {
+                        'n' => text.push('\n'),
+                        'r' => text.push('\r'),
+                        't' => text.push('\t'),
+                        '"' | '\\' | '$' | '`' => text.push(escaped),
+                        _ => {
+                            text.push('\\');
+                            text.push(escaped);
+                        },
+                    }
+                },
+                None => text.push('\\'),
+            },
+            '$' => match scan_variable(rest) {
+                Some((name, default, len)) => {
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(Segment::Variable {
+                        name: name.to_string(),
+                        default: default.map(|default| scan_segments(default, escapes)),
+                        source: format!("${}", &rest[..len]),
+                    });
+                    rest = &rest[len..];
+                },
+                None => text.push('$'),
+            },
+            // A line break inside a quoted value of a CRLF file.
+            '\r' if rest.starts_with('\n') => {},
+            _ => text.push(ch),
+        }
+    }
+
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+
+    segments
+}
+
+/// Renders segments the way the value is written, leaving references as is.
+pub(crate) fn literal_value(segments: &[Segment]) -> String {
+    segments
+        .iter()
+        .map(|segment| match segment {
+            Segment::Text(text) => text.as_str(),
+            Segment::Variable { source, .. } => source.as_str(),
+        })
+        .collect()
+}
+
+// Returns the length of a quoted value including both quotes, or `None`
+// when the closing quote is missing. `rest` starts at the opening quote.
+fn quoted_len(rest: &str) -> Option<usize> {
+    if let Some(body) = rest.strip_prefix('\'') {
+        body.find('\'').map(|end| end + 2)
+    } else if let Some(body) = rest.strip_prefix('"') {
+        let mut escaped = false;
+        for (index, ch) in body.char_indices() {
+            match ch {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => return Some(index + 2),
+                _ => {},
+            }
+        }
+        None
+    } else {
+        None
+    }
+}
+
+// Length of a bare value: it ends where a `#` preceded by whitespace starts
+// an inline comment, with trailing whitespace trimmed.
+fn bare_len(rest: &str, after_whitespace: bool) -> usize {
+    let mut prev_is_whitespace = after_whitespace;
+
+    for (index, ch) in rest.char_indices() {
+        if ch == '#' && prev_is_whitespace {
+            return rest[..index].trim_end().len();
+        }
+        prev_is_whitespace = ch.is_whitespace();
+    }
+
+    rest.trim_end().len()
+}
+
+/// Whether `key` is a POSIX shell identifier: ASCII letters, digits and
+/// `_`, not starting with a digit.
+pub(crate) fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Whether `key` reads back as itself once written as `KEY=value`.
+pub(crate) fn is_writable_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.trim() == key
+        && !key.starts_with('#')
+        && !key.contains(['=', '\n', '\r'])
+        && strip_export_prefix(key).0 == key
+}
+
+fn strip_export_prefix(line: &str) -> (&str, bool) {
+    match line.strip_prefix("export") {
+        Some(rest) if rest.starts_with([' ', '\t']) => (rest.trim_start(), true),
+        _ => (line, false),
+    }
+}
+
+// Byte positions of the parts of an assignment line.
+struct Assignment {
+    key: Range<usize>,
+    // Where the value starts, after any whitespace following `=`.
+    value_start: usize,
+    after_whitespace: bool,
+    exported: bool,
+}
+
+// Leading whitespace is skipped before anything else, so an indented `#`
+// still starts a comment.
+fn split_assignment(line: &str) -> Option<Assignment> {
+    let body = line.trim_start();
+    if body.starts_with('#') {
+        return None;
+    }
+
+    let (assignment, exported) = strip_export_prefix(body);
+    let offset = line.len() - assignment.len();
+    let equal = offset + assignment.find('=')?;
+
+    let key_part = &line[offset..equal];
+    let key_start = offset + (key_part.len() - key_part.trim_start().len());
+    let key_end = offset + key_part.trim_end().len();
+
+    let rest = &line[equal + 1..];
+    let value_start = equal + 1 + (rest.len() - rest.trim_start().len());
+
+    Some(Assignment {
+        key: key_start..key_end,
+        value_start,
+        after_whitespace: value_start > equal + 1,
+        exported,
+    })
+}
+
+// The char index of the quote that opens the value of `line` when the
+// line does not close it.
+fn open_quote(line: &str) -> Option<usize> {
+    let assignment = split_assignment(line)?;
+    let rest = &line[assignment.value_start..];
+
+    if rest.starts_with(['"', '\'']) && quoted_len(rest).is_none() {
+        Some(line[..assignment.value_start].chars().count())
+    } else {
+        None
+    }
+}
+
+// The index just past the quote that closes the one at `start`.
+fn closing_quote(content: &[u8], start: usize) -> Option<usize> {
+    let quote = content[start];
+    let mut escaped = false;
+
+    for (index, &byte) in content.iter().enumerate().skip(start + 1) {
+        match byte {
+            _ if escaped => escaped = false,
+            b'\\' if quote == b'"' => escaped = true,
+            _ if byte == quote => return Some(index + 1),
+            _ => {},
+        }
+    }
+
+    None
+}
+
+// The index just past the physical line that holds `index`.
+fn line_end(content: &[u8], index: usize) -> usize {
+    match content[index..].iter().position(|&byte| byte == b'\n') {
+        Some(position) => index + position + 1,
+        None => content.len(),
+    }
+}
+
+/// Maps every byte to the char with the same number, so that text which is
+/// not valid UTF-8 can still be parsed for its quotes, `=` and `#`.
+/// `latin1_encode` turns parsed parts back into the original bytes.
+pub(crate) fn latin1_decode(bytes: &[u8]) -> String {
+    bytes.iter().map(|&byte| char::from(byte)).collect()
+}
+
+pub(crate) fn latin1_encode(s: &str) -> Vec<u8> {
+    s.chars().map(|ch| ch as u8).collect()
+}
+
+/// Splits `content` into logical lines, each keeping its trailing newline.
+/// Physical lines are joined while a quoted value is still open, so a
+/// multiline value ends up as one logical line. A quote that is never
+/// closed only spoils its own line. Lines need not be UTF-8.
+pub(crate) fn split_logical_lines(content: &[u8]) -> Vec<&[u8]> {
+    let mut logical_lines = vec![];
+    let mut start = 0;
+
+    while start < content.len() {
+        let mut end = line_end(content, start);
+
+        // A search that fails runs to the end of the file, but then no
+        // later line can open a value with the same quote either: that
+        // quote would have closed this one. So at most two searches fail.
+        if let Some(quote) = open_quote(strip_newline(&latin1_decode(&content[start..end]))) {
+            if let Some(close) = closing_quote(content, start + quote) {
+                end = line_end(content, close - 1);
+            }
+        }
+
+        logical_lines.push(&content[start..end]);
+        start = end;
+    }
+
+    logical_lines
+}
+
+/// Strips a trailing `\n` or `\r\n`.
+pub(crate) fn strip_newline(line: &str) -> &str {
+    match line.strip_suffix('\n') {
+        Some(line) => line.strip_suffix('\r').unwrap_or(line),
+        None => line,
+    }
+}
+
+/// The key a line assigns to, even when its value cannot be parsed.
+pub(crate) fn assigned_key(line: &str) -> Option<&str> {
+    split_assignment(line).map(|assignment| &line[assignment.key])
+}
+
+pub(crate) enum EnvPairParseError {
+    Comment,
+    Empty,
+    Unknown,
+}
+
+/// A parsed entry with the byte ranges of its key and value in the line, so
+/// that either can be replaced while keeping the rest of the line as is.
+pub(crate) struct Entry {
+    pub(crate) env_pair: EnvPair,
+    pub(crate) segments: Vec<Segment>,
+    pub(crate) key_span: Range<usize>,
+    pub(crate) value_span: Range<usize>,
+}
+
+pub(crate) fn line_to_env_pair(line: &str) -> Result<EnvPair, EnvPairParseError> {
+    line_to_entry(line).map(|entry| entry.env_pair)
+}
+
+// Single-quoted values are literal, double-quoted values decode escapes and
+// both double-quoted and bare values may hold references. After a closing
+// quote only whitespace and an inline comment may follow.
+pub(crate) fn line_to_entry(line: &str) -> Result<Entry, EnvPairParseError> {
+    let assignment = match split_assignment(line) {
+        Some(assignment) => assignment,
+        None if line.trim().is_empty() => return Err(EnvPairParseError::Empty),
+        None if line.trim_start().starts_with('#') => return Err(EnvPairParseError::Comment),
+        None => return Err(EnvPairParseError::Unknown),
+    };
+
+    let start = assignment.value_start;
+    let rest = &line[start..];
+
+    let (value_span, segments) = if rest.starts_with(['"', '\'']) {
+        let len = quoted_len(rest).ok_or(EnvPairParseError::Unknown)?;
+        let trailer = line[start + len..].trim_start();
+        if !(trailer.is_empty() || trailer.starts_with('#')) {
+            return Err(EnvPairParseError::Unknown);
+        }
+
+        let body = &rest[1..len - 1];
+        let segments = if rest.starts_with('"') {
+            scan_segments(body, true)
+        } else {
+            vec![Segment::Text(body.replace("\r\n", "\n"))]
+        };
+        (start..start + len, segments)
+    } else {
+        let len = bare_len(rest, assignment.after_whitespace);
+        (start..start + len, scan_segments(&rest[..len], false))
+    };
+
+    Ok(Entry {
+        env_pair: EnvPair {
+            key: line[assignment.key.clone()].to_string(),
+            value: literal_value(&segments),
+            exported: assignment.exported,
+        },
+        segments,
+        key_span: assignment.key,
+        value_span,
+    })
+}
+
+/// The path named by an include directive, `# @include PATH`, also
+/// spelled `# @source PATH`.
+pub(crate) fn include_path(line: &str) -> Option<&str> {
+    let body = line.trim_start().strip_prefix('#')?.trim_start();
+    let path = body.strip_prefix("@include").or_else(|| body.strip_prefix("@source"))?;
+
+    if !path.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    Some(path.trim()).filter(|path| !path.is_empty())
+}
+
+/// The assignment a comment line disables, as in `# DEBUG=true`. Comments
+/// whose text merely contains `=` are not taken for assignments: the key
+/// must be a single word.
+pub(crate) fn commented_env_pair(line: &str) -> Option<EnvPair> {
+    let body = line.trim_start().strip_prefix('#')?;
+    let env_pair = line_to_env_pair(body.trim_start()).ok()?;
+
+    if env_pair.key.is_empty() || env_pair.key.contains(char::is_whitespace) {
+        return None;
+    }
+
+    Some(env_pair)
+}
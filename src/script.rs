@@ -0,0 +1,66 @@
+use serde::Deserialize;
+
+use crate::parser;
+use crate::EnvDbError;
+
+/// A single edit of an `apply` script.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Operation {
+    Set { key: String, value: String },
+    Delete { key: String },
+    Rename { from: String, to: String },
+}
+
+fn script_error(line: usize, message: impl Into<String>) -> EnvDbError {
+    EnvDbError::Script { line, message: message.into() }
+}
+
+fn parse_script_line(line: &str, line_number: usize) -> Result<Operation, EnvDbError> {
+    let (command, args) = line.split_once([' ', '\t']).unwrap_or((line, ""));
+    let args = args.trim();
+
+    match command {
+        "set" => match parser::line_to_env_pair(args) {
+            Ok(env_pair) => Ok(Operation::Set { key: env_pair.key, value: env_pair.value }),
+            Err(_) => Err(script_error(line_number, "expected `set KEY=VALUE`")),
+        },
+        "delete" => match args.split_whitespace().collect::<Vec<_>>()[..] {
+            [key] => Ok(Operation::Delete { key: key.to_string() }),
+            _ => Err(script_error(line_number, "expected `delete KEY`")),
+        },
+        "rename" => match args.split_whitespace().collect::<Vec<_>>()[..] {
+            [from, to] => Ok(Operation::Rename { from: from.to_string(), to: to.to_string() }),
+            _ => Err(script_error(line_number, "expected `rename FROM TO`")),
+        },
+        _ => Err(script_error(line_number, format!("unknown operation `{}`", command))),
+    }
+}
+
+/// Parses an `apply` script. Two forms are accepted:
+///
+/// - a JSON array of `{"op": "set", "key": .., "value": ..}`,
+///   `{"op": "delete", "key": ..}` and `{"op": "rename", "from": .., "to": ..}`
+///   objects;
+/// - one operation per line: `set KEY=VALUE` (the value uses env file
+///   quoting), `delete KEY` and `rename FROM TO`. Blank lines and lines
+///   starting with `#` are ignored.
+pub fn parse_script(content: &str) -> Result<Vec<Operation>, EnvDbError> {
+    if content.trim_start().starts_with('[') {
+        return serde_json::from_str(content).map_err(|err| script_error(err.line(), err.to_string()));
+    }
+
+    let mut operations = vec![];
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        operations.push(parse_script_line(line, index + 1)?);
+    }
+
+    Ok(operations)
+}
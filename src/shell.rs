@@ -0,0 +1,114 @@
+use crate::parser;
+use crate::EnvPair;
+
+/// A shell to render entries for with `render_exports`, in a form its
+/// `eval` or `source` reads back as the exact value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shell {
+    /// `export KEY='value'`, read by bash, zsh and other POSIX shells.
+    Posix,
+    /// `set -gx KEY 'value'`.
+    Fish,
+    /// `$env:KEY = 'value'`.
+    PowerShell,
+    /// A single `load-env { "KEY": "value" }` record.
+    Nushell,
+}
+
+impl Shell {
+    /// Whether the shell can name an environment variable `key`. Nushell
+    /// and PowerShell can name any key; POSIX shells and fish only
+    /// identifiers.
+    pub fn accepts_key(&self, key: &str) -> bool {
+        match self {
+            Shell::Posix | Shell::Fish => parser::is_identifier(key),
+            Shell::PowerShell | Shell::Nushell => true,
+        }
+    }
+}
+
+/// Renders `env_pairs` as statements that set them in `shell`. Entries
+/// whose key the shell cannot name are left out.
+pub fn render_exports(shell: Shell, env_pairs: &[EnvPair]) -> String {
+    let env_pairs = env_pairs.iter().filter(|env_pair| shell.accepts_key(&env_pair.key));
+
+    match shell {
+        Shell::Posix => env_pairs
+            .map(|env_pair| format!("export {}={}\n", env_pair.key, posix_quote(&env_pair.value)))
+            .collect(),
+        Shell::Fish => env_pairs
+            .map(|env_pair| format!("set -gx {} {}\n", env_pair.key, fish_quote(&env_pair.value)))
+            .collect(),
+        Shell::PowerShell => env_pairs
+            .map(|env_pair| {
+                let variable = if parser::is_identifier(&env_pair.key) {
+                    format!("$env:{}", env_pair.key)
+                } else {
+                    format!("${{env:{}}}", powershell_escape_braced(&env_pair.key))
+                };
+                format!("{} = {}\n", variable, powershell_quote(&env_pair.value))
+            })
+            .collect(),
+        Shell::Nushell => {
+            let fields: Vec<String> = env_pairs
+                .map(|env_pair| format!("{}: {}", nushell_quote(&env_pair.key), nushell_quote(&env_pair.value)))
+                .collect();
+            format!("load-env {{ {} }}\n", fields.join(", "))
+        },
+    }
+}
+
+// Nothing is special between single quotes, so only the quote itself needs
+// closing, escaping and reopening.
+fn posix_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+// Fish single quotes only treat `\'` and `\\` as escapes.
+fn fish_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', r"\\").replace('\'', r"\'"))
+}
+
+// PowerShell takes the typographic single quotes for quotes as well; each
+// is escaped by doubling it.
+fn powershell_quote(value: &str) -> String {
+    let mut quoted = String::from("'");
+    for ch in value.chars() {
+        if matches!(ch, '\'' | '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{201b}') {
+            quoted.push(ch);
+        }
+        quoted.push(ch);
+    }
+    quoted.push('\'');
+    quoted
+}
+
+fn powershell_escape_braced(key: &str) -> String {
+    let mut escaped = String::new();
+    for ch in key.chars() {
+        if matches!(ch, '`' | '{' | '}') {
+            escaped.push('`');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+// Nushell double-quoted strings take C-style escapes and, unlike `$"..."`
+// strings, do not interpolate.
+fn nushell_quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for ch in value.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            ch if ch.is_control() => quoted.push_str(&format!("\\u{{{:x}}}", ch as u32)),
+            ch => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,171 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tempfile::NamedTempFile;
+
+use crate::EnvDbError;
+
+#[derive(Clone, Default)]
+pub struct FileOptions {
+    /// When the target is a symlink, rewrite the file it points to instead of
+    /// replacing the link itself with a regular file.
+    pub follow_symlinks: bool,
+    /// How long to wait for another envdb process to release the file.
+    /// `None` waits forever.
+    pub lock_timeout: Option<Duration>,
+    /// Reports lines skipped while reading, such as ones that do not
+    /// parse. `None` prints the messages to stderr.
+    pub warn: Option<fn(&str)>,
+}
+
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(20);
+
+// Same limit as Linux's MAXSYMLINKS.
+const MAX_SYMLINK_DEPTH: usize = 40;
+
+fn resolve_symlinks(path: &Path) -> io::Result<PathBuf> {
+    let mut resolved = path.to_path_buf();
+
+    for _ in 0..MAX_SYMLINK_DEPTH {
+        match fs::symlink_metadata(&resolved) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                let link = fs::read_link(&resolved)?;
+                resolved = match resolved.parent() {
+                    Some(parent) => parent.join(link),
+                    None => link,
+                };
+            },
+            _ => return Ok(resolved),
+        }
+    }
+
+    Err(io::Error::other(format!("Too many levels of symbolic links: {}", path.display())))
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+fn target_path(target_env_path: &Path, options: &FileOptions) -> io::Result<PathBuf> {
+    if options.follow_symlinks {
+        resolve_symlinks(target_env_path)
+    } else {
+        Ok(target_env_path.to_path_buf())
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum LockMode {
+    Shared,
+    Exclusive,
+}
+
+/// An advisory lock on an env file, released when dropped.
+pub(crate) struct FileLock {
+    _file: fs::File,
+}
+
+#[cfg(unix)]
+fn is_same_file(file: &fs::File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(locked), Ok(current)) => locked.dev() == current.dev() && locked.ino() == current.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_same_file(_file: &fs::File, _path: &Path) -> bool {
+    true
+}
+
+/// Takes an advisory lock on `target_env_path`, polling until
+/// `options.lock_timeout` runs out.
+///
+/// Writers replace the file by renaming a new one over it, so a lock taken on
+/// the old file protects nothing once the rename happened. After acquiring
+/// the lock we therefore check that the path still refers to the locked file
+/// and start over on the new file otherwise.
+pub(crate) fn lock(target_env_path: &Path, mode: LockMode, options: &FileOptions) -> Result<FileLock, EnvDbError> {
+    let io_error = |err| EnvDbError::Io { path: target_env_path.to_path_buf(), source: err };
+
+    let target_path = target_path(target_env_path, options).map_err(io_error)?;
+    let deadline = options.lock_timeout.map(|timeout| Instant::now() + timeout);
+
+    loop {
+        let file = fs::File::open(&target_path).map_err(io_error)?;
+        let locked = match mode {
+            LockMode::Shared => file.try_lock_shared(),
+            LockMode::Exclusive => file.try_lock(),
+        };
+
+        match locked {
+            Ok(()) => {
+                if is_same_file(&file, &target_path) {
+                    return Ok(FileLock { _file: file });
+                }
+            },
+            Err(fs::TryLockError::WouldBlock) => {
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    return Err(EnvDbError::Lock {
+                        path: target_env_path.to_path_buf(),
+                        timeout: options.lock_timeout.unwrap_or_default(),
+                    });
+                }
+                thread::sleep(LOCK_RETRY_INTERVAL);
+            },
+            Err(fs::TryLockError::Error(err)) => return Err(io_error(err)),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn copy_ownership(metadata: &fs::Metadata, file: &fs::File) {
+    use std::os::unix::fs::MetadataExt;
+
+    // Best effort: only root may hand the file to another user, and keeping
+    // our own ownership is still better than failing the whole write.
+    let _ = std::os::unix::fs::fchown(file, Some(metadata.uid()), Some(metadata.gid()));
+}
+
+#[cfg(not(unix))]
+fn copy_ownership(_metadata: &fs::Metadata, _file: &fs::File) {}
+
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    if cfg!(unix) {
+        fs::File::open(dir)?.sync_all()
+    } else {
+        Ok(())
+    }
+}
+
+/// Replaces the content of `target_env_path` atomically: the new content is
+/// written to a temporary file next to the target, given the original
+/// permissions and ownership, flushed to disk and renamed into place.
+pub(crate) fn write_atomic(target_env_path: &Path, content: &[u8], options: &FileOptions) -> Result<(), EnvDbError> {
+    let write_error = |err| EnvDbError::Write { path: target_env_path.to_path_buf(), source: err };
+
+    let target_path = target_path(target_env_path, options).map_err(write_error)?;
+    let dir = parent_dir(&target_path);
+
+    let mut tmpfile = NamedTempFile::new_in(dir).map_err(write_error)?;
+    tmpfile.write_all(content).map_err(write_error)?;
+
+    if let Ok(metadata) = fs::metadata(&target_path) {
+        tmpfile.as_file().set_permissions(metadata.permissions()).map_err(write_error)?;
+        copy_ownership(&metadata, tmpfile.as_file());
+    }
+
+    tmpfile.as_file().sync_all().map_err(write_error)?;
+    tmpfile.persist(&target_path).map_err(|err| write_error(err.error))?;
+    sync_dir(dir).map_err(write_error)?;
+
+    Ok(())
+}
@@ -0,0 +1,268 @@
+use std::process::Command;
+
+#[test]
+fn test_apply_successful() {
+    let origin_env_path = "tests/.test-env";
+    let target_env_path = "tests/.test-env-apply-successful";
+    let script_path = "tests/.test-env-apply-successful-script";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+    std::fs::write(
+        script_path,
+        "# rotate keys\nset SOME_APP_KEY=\"rotated key\"\ndelete SOME_APP_SECRET\nrename EXPORTED_KEY RENAMED_KEY\nset NEW_KEY=new\n",
+    )
+    .expect("Failed to write script");
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("apply")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg(script_path)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert!(content.contains("\nSOME_APP_KEY='rotated key'\n"));
+    assert!(!content.contains("SOME_APP_SECRET"));
+    assert!(content.contains("\nexport RENAMED_KEY=exported-value\n"));
+    assert!(!content.contains("EXPORTED_KEY"));
+    assert!(content.ends_with("\nNEW_KEY=new\n"));
+}
+
+#[test]
+fn test_apply_successful_with_json_and_dry_run() {
+    use std::io::Write;
+
+    let origin_env_path = "tests/.test-env";
+    let target_env_path = "tests/.test-env-apply-successful-with-json-and-dry-run";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    let mut child = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("apply")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("--dry-run")
+        .arg("-")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to execute command");
+    child
+        .stdin
+        .take()
+        .expect("Failed to open stdin")
+        .write_all(br#"[{"op": "set", "key": "SOME_APP_KEY", "value": "new-app-key"}, {"op": "delete", "key": "SOME_APP_SECRET"}]"#)
+        .expect("Failed to write stdin");
+    let output = child.wait_with_output().expect("Failed to wait command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(
+        stdout,
+        format!(
+            "--- {0}\n+++ {0}\n@@ -1,7 +1,6 @@\n # This is comment\n \n-SOME_APP_KEY=app-key\n-SOME_APP_SECRET=app-secret\n+SOME_APP_KEY=new-app-key\n SSH_AUTH_SOCK_DOCKER_HOST=/some/host-socket.sock\n DOUBLE_QUOTED_KEY=\"quoted value\"\n SINGLE_QUOTED_KEY='single $quoted'\n",
+            target_env_path
+        )
+    );
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    let origin_content = std::fs::read_to_string(origin_env_path).expect("Failed to read env file");
+    assert_eq!(content, origin_content);
+}
+
+#[test]
+fn test_apply_failed_by_missing_rename_source() {
+    let origin_env_path = "tests/.test-env";
+    let target_env_path = "tests/.test-env-apply-failed-by-missing-rename-source";
+    let script_path = "tests/.test-env-apply-failed-by-missing-rename-source-script";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+    std::fs::write(script_path, "set SOME_APP_KEY=changed\nrename NOT_FOUND_KEY OTHER_KEY\n").expect("Failed to write script");
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("apply")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg(script_path)
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(9));
+
+    let stdout = String::from_utf8(output.stderr).expect("Failed to parse stdout as UTF-8");
+    assert!(stdout.trim().ends_with("Failed to apply operation 2: cannot rename NOT_FOUND_KEY, the key does not exist"));
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert!(content.contains("\nSOME_APP_KEY=app-key\n"));
+}
+
+#[test]
+fn test_apply_failed_by_invalid_script() {
+    let script_path = "tests/.test-env-apply-failed-by-invalid-script";
+    std::fs::write(script_path, "set SOME_APP_KEY=changed\n\nupsert OTHER_KEY\n").expect("Failed to write script");
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("apply")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg(script_path)
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(10));
+
+    let stdout = String::from_utf8(output.stderr).expect("Failed to parse stdout as UTF-8");
+    assert!(stdout.trim().ends_with("Failed to parse the script at line 3: unknown operation `upsert`"));
+}
+
+#[test]
+fn test_apply_failed_by_invalid_key() {
+    let origin_env_path = "tests/.test-env";
+    let target_env_path = "tests/.test-env-apply-failed-by-invalid-key";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    for script in [
+        r#"[{"op":"set","key":"X=Y","value":"v"}]"#,
+        r#"[{"op":"set","key":"","value":"e"}]"#,
+        r#"[{"op":"rename","from":"SOME_APP_KEY","to":"1 B"}]"#,
+        "set 9BAD=1\n",
+    ] {
+        let mut child = Command::new("cargo")
+            .arg("run")
+            .arg("--")
+            .arg("apply")
+            .arg("--target-env")
+            .arg(target_env_path)
+            .arg("-")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .expect("Failed to execute command");
+        std::io::Write::write_all(&mut child.stdin.take().unwrap(), script.as_bytes()).expect("Failed to write script");
+        let output = child.wait_with_output().expect("Failed to execute command");
+
+        assert_eq!(output.status.code(), Some(12), "script: {}", script);
+    }
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert_eq!(content, std::fs::read_to_string(origin_env_path).expect("Failed to read env file"));
+}
+
+#[test]
+fn test_apply_successful_with_allow_any_key() {
+    let origin_env_path = "tests/.test-env";
+    let target_env_path = "tests/.test-env-apply-successful-with-allow-any-key";
+    let script_path = "tests/.test-env-apply-successful-with-allow-any-key-script";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+    std::fs::write(script_path, "set app.name=envdb\n").expect("Failed to write script");
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("apply")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("--allow-any-key")
+        .arg(script_path)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert!(content.ends_with("\napp.name=envdb\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_apply_successful_with_non_utf8_path() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let target_env_path = std::ffi::OsStr::from_bytes(b"tests/.test-env-apply-successful-with-non-utf8-path-\xff");
+    let script_path = "tests/.test-env-apply-successful-with-non-utf8-path-script";
+    if let Err(err) = std::fs::copy("tests/.test-env", target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+    std::fs::write(script_path, "set SOME_APP_KEY=changed\n").expect("Failed to write script");
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("apply")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("--dry-run")
+        .arg(script_path)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert!(stdout.contains("+SOME_APP_KEY=changed\n"));
+}
+
+#[test]
+fn test_apply_successful_with_duplicates() {
+    let origin_env_path = "tests/.duplicate-env";
+    let target_env_path = "tests/.test-env-apply-successful-with-duplicates";
+    let script_path = "tests/.test-env-apply-successful-with-duplicates-script";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+    std::fs::write(script_path, "set APP_KEY=changed\n").expect("Failed to write script");
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("apply")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("--duplicates")
+        .arg("first")
+        .arg(script_path)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert_eq!(content, "APP_KEY=changed\nOTHER_KEY=other\nAPP_KEY=second\n");
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("apply")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("--duplicates")
+        .arg("error")
+        .arg(script_path)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(!output.status.success());
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert_eq!(content, "APP_KEY=changed\nOTHER_KEY=other\nAPP_KEY=second\n");
+}
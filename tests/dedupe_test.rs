@@ -0,0 +1,77 @@
+use std::process::Command;
+
+#[test]
+fn test_dedupe_successful() {
+    let origin_env_path = "tests/.duplicate-env";
+    let target_env_path = "tests/.test-env-dedupe-successful";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("dedupe")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("--duplicates")
+        .arg("first")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout.trim(), "dropped line 3: APP_KEY=second");
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert_eq!(content, "APP_KEY=first\nOTHER_KEY=other\n");
+}
+
+#[test]
+fn test_dedupe_successful_with_last() {
+    let origin_env_path = "tests/.duplicate-env";
+    let target_env_path = "tests/.test-env-dedupe-successful-with-last";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("dedupe")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert_eq!(content, "OTHER_KEY=other\nAPP_KEY=second\n");
+}
+
+#[test]
+fn test_dedupe_failed_by_duplicates() {
+    let origin_env_path = "tests/.duplicate-env";
+    let target_env_path = "tests/.test-env-dedupe-failed-by-duplicates";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("dedupe")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("--duplicates")
+        .arg("error")
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(3));
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert_eq!(content, "APP_KEY=first\nOTHER_KEY=other\nAPP_KEY=second\n");
+}
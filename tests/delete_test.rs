@@ -83,3 +83,153 @@ fn test_delete_failed_by_not_found_env_path() {
     let stdout = String::from_utf8(output.stderr).expect("Failed to parse stdout as UTF-8");
     assert!(stdout.trim().ends_with("Failed to open the file: tests/.not-found-env"));
 }
+
+#[test]
+fn test_delete_successful_with_multiline() {
+    let origin_env_path = "tests/.test-env";
+    let target_env_path = "tests/.test-env-delete-successful-with-multiline";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("delete")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("MULTILINE_KEY")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert!(!content.contains("MULTILINE_KEY"));
+    assert!(!content.contains("first line"));
+    assert!(!content.contains("-----END KEY-----"));
+    assert!(content.contains("SOME_APP_KEY=app-key\n"));
+}
+
+#[test]
+fn test_delete_successful_with_all_layers() {
+    let target_env_path = "tests/.test-env-delete-successful-with-all-layers";
+    for suffix in ["", ".production", ".local"] {
+        let origin_env_path = format!("tests/.layer-env{}", suffix);
+        if let Err(err) = std::fs::copy(origin_env_path, format!("{}{}", target_env_path, suffix)) {
+            panic!("Fail to copy origin env to test own env: {}", err);
+        }
+    }
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("delete")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("--mode")
+        .arg("production")
+        .arg("--all-layers")
+        .arg("APP_MODE")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(
+        stdout,
+        format!("deleted from {0}\ndeleted from {0}.production\n", target_env_path)
+    );
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("--mode")
+        .arg("production")
+        .arg("APP_MODE")
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_delete_successful_with_all_layers_naming_a_file_twice() {
+    let origin_env_path = "tests/.layer-env";
+    let target_env_path = "tests/.test-env-delete-successful-with-all-layers-naming-a-file-twice";
+    let link_path = "tests/.test-env-delete-successful-with-all-layers-naming-a-file-twice-link";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+    let _ = std::fs::remove_file(link_path);
+    std::os::unix::fs::symlink(target_env_path.trim_start_matches("tests/"), link_path).expect("Failed to create symlink");
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("delete")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("--target-env")
+        .arg(format!("./{}", target_env_path))
+        .arg("--target-env")
+        .arg(link_path)
+        .arg("--lock-timeout")
+        .arg("1")
+        .arg("--all-layers")
+        .arg("APP_MODE")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout, format!("deleted from {}\n", target_env_path));
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert!(!content.contains("APP_MODE"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_delete_successful_with_all_layers_json_and_non_utf8_path() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let target_env_path = "tests/.test-env-delete-successful-with-all-layers-json-and-non-utf8-path";
+    let overlay_path = std::ffi::OsStr::from_bytes(b"tests/.test-env-delete-successful-with-all-layers-json-and-non-utf8-path-\xff");
+    if let Err(err) = std::fs::copy("tests/.layer-env", target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+    if let Err(err) = std::fs::copy("tests/.layer-env.production", overlay_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("--output")
+        .arg("json")
+        .arg("delete")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("--target-env")
+        .arg(overlay_path)
+        .arg("--all-layers")
+        .arg("APP_MODE")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let records: serde_json::Value = serde_json::from_slice(&output.stdout).expect("Failed to parse stdout as JSON");
+    assert_eq!(records[0]["file"], target_env_path);
+    assert_eq!(
+        records[1]["file"],
+        "tests/.test-env-delete-successful-with-all-layers-json-and-non-utf8-path-\u{fffd}"
+    );
+}
@@ -0,0 +1,135 @@
+use envdb::{EnvDocument, EnvPair, LineKind};
+
+#[test]
+fn test_document_round_trip_untouched() {
+    let content = std::fs::read_to_string("tests/.test-env").expect("Failed to read env file");
+    let document = EnvDocument::parse(&content);
+
+    assert_eq!(document.to_string(), content);
+}
+
+#[test]
+fn test_document_classifies_lines() {
+    let document = EnvDocument::parse("# comment\n\nKEY=value\nbroken line\nMULTI=\"a\nb\"\n");
+    let kinds: Vec<&str> = document
+        .lines()
+        .iter()
+        .map(|line| match line.kind() {
+            LineKind::Entry(_) => "entry",
+            LineKind::Comment => "comment",
+            LineKind::Blank => "blank",
+            LineKind::Unparsed => "unparsed",
+            LineKind::Undecodable => "undecodable",
+        })
+        .collect();
+
+    assert_eq!(kinds, ["comment", "blank", "entry", "unparsed", "entry"]);
+    assert_eq!(document.lines()[4].raw(), "MULTI=\"a\nb\"\n");
+}
+
+#[test]
+fn test_document_classifies_indented_lines() {
+    let document = EnvDocument::parse("  # FOO=disabled\n   KEY=v\n  \n# see a=b\n");
+    let kinds: Vec<&str> = document
+        .lines()
+        .iter()
+        .map(|line| match line.kind() {
+            LineKind::Entry(_) => "entry",
+            LineKind::Comment => "comment",
+            LineKind::Blank => "blank",
+            LineKind::Unparsed => "unparsed",
+            LineKind::Undecodable => "undecodable",
+        })
+        .collect();
+
+    assert_eq!(kinds, ["comment", "entry", "blank", "comment"]);
+    assert_eq!(document.get("KEY").map(|env_pair| env_pair.value.as_str()), Some("v"));
+
+    let commented: Vec<String> = document
+        .lines()
+        .iter()
+        .filter_map(|line| line.commented_env_pair())
+        .map(|env_pair| env_pair.key)
+        .collect();
+    assert_eq!(commented, ["FOO"]);
+}
+
+#[test]
+fn test_document_get_set_remove() {
+    let mut document = EnvDocument::parse("# comment\nexport FOO=1\nBAR=2");
+
+    assert_eq!(document.get("FOO").map(|env_pair| env_pair.value.as_str()), Some("1"));
+    assert!(document.set("FOO", "new value"));
+    assert!(!document.set("BAZ", "3"));
+    assert!(document.remove("BAR"));
+    assert!(!document.remove("NOT_FOUND"));
+    document.append(EnvPair {
+        key: "QUX".to_string(),
+        value: "4".to_string(),
+        exported: true,
+    });
+
+    assert_eq!(document.to_string(), "# comment\nexport FOO='new value'\nBAZ=3\nexport QUX=4");
+
+    let keys: Vec<&str> = document.iter().map(|env_pair| env_pair.key.as_str()).collect();
+    assert_eq!(keys, ["FOO", "BAZ", "QUX"]);
+
+    let values: Vec<&str> = (&document).into_iter().map(|env_pair| env_pair.value.as_str()).collect();
+    assert_eq!(values, ["new value", "3", "4"]);
+}
+
+#[test]
+fn test_document_get_set_follow_default_duplicate_policy() {
+    let mut document = EnvDocument::parse("A=1\nA=2\n");
+
+    assert_eq!(document.get("A").map(|env_pair| env_pair.value.as_str()), Some("2"));
+    assert!(document.set("A", "3"));
+    assert_eq!(document.to_string(), "A=1\nA=3\n");
+
+    document
+        .apply(&[envdb::Operation::Set { key: "A".to_string(), value: "4".to_string() }])
+        .unwrap();
+    assert_eq!(document.to_string(), "A=1\nA=4\n");
+}
+
+#[test]
+fn test_document_keeps_bom_and_crlf() {
+    let content = "\u{feff}FIRST=1\r\nMULTI=\"a\r\nb\"\r\nSINGLE='c\r\nd'\r\n";
+    let mut document = EnvDocument::parse(content);
+
+    assert_eq!(document.to_string(), content);
+    assert_eq!(document.get("FIRST").map(|env_pair| env_pair.value.as_str()), Some("1"));
+    assert_eq!(document.get("MULTI").map(|env_pair| env_pair.value.as_str()), Some("a\nb"));
+    assert_eq!(document.get("SINGLE").map(|env_pair| env_pair.value.as_str()), Some("c\nd"));
+
+    document.set("FIRST", "2");
+    document.set("NEW", "3");
+    assert_eq!(
+        document.to_string(),
+        "\u{feff}FIRST=2\r\nMULTI=\"a\r\nb\"\r\nSINGLE='c\r\nd'\r\nNEW=3\r\n"
+    );
+}
+
+#[test]
+fn test_document_keeps_undecodable_lines() {
+    let content = b"GOOD_KEY=good\nLATIN1_KEY=caf\xe9\n# caf\xe9\n";
+    let mut document = EnvDocument::parse_bytes(content);
+
+    assert!(matches!(document.lines()[1].kind(), LineKind::Undecodable));
+    assert_eq!(document.lines()[1].raw_value(), Some(b"caf\xe9".to_vec()));
+    assert!(matches!(
+        document.get_with("LATIN1_KEY", Default::default()),
+        Err(envdb::EnvDbError::Encoding { line: 2, .. })
+    ));
+
+    document.set("GOOD_KEY", "better");
+    assert_eq!(document.to_bytes(), b"GOOD_KEY=better\nLATIN1_KEY=caf\xe9\n# caf\xe9\n");
+}
+
+#[test]
+fn test_document_includes() {
+    let document = EnvDocument::parse("# @include ../common.env\n  #@source  shared.env  \n# @included no\n# see @include\nKEY=1\n");
+
+    let includes: Vec<&str> = document.includes().collect();
+    assert_eq!(includes, ["../common.env", "shared.env"]);
+}
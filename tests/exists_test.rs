@@ -0,0 +1,62 @@
+use std::process::Command;
+
+fn exists(args: &[&str]) -> Option<i32> {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("exists")
+        .args(args)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.stdout.is_empty());
+    output.status.code()
+}
+
+#[test]
+fn test_exists_successful() {
+    assert_eq!(exists(&["--target-env", "tests/.test-env", "SOME_APP_KEY", "SOME_APP_SECRET"]), Some(0));
+}
+
+#[test]
+fn test_exists_failed_by_missing_or_empty_key() {
+    assert_eq!(exists(&["--target-env", "tests/.test-env", "SOME_APP_KEY", "NOT_FOUND_KEY"]), Some(1));
+    assert_eq!(exists(&["--target-env", "tests/.test-env", "SOME_APP_KEY", "EMPTY_KEY"]), Some(11));
+    assert_eq!(exists(&["--target-env", "tests/.test-env", "NOT_FOUND_KEY", "EMPTY_KEY"]), Some(1));
+}
+
+#[test]
+fn test_exists_successful_with_any() {
+    assert_eq!(exists(&["--target-env", "tests/.test-env", "--any", "NOT_FOUND_KEY", "SOME_APP_KEY"]), Some(0));
+    assert_eq!(exists(&["--target-env", "tests/.test-env", "--any", "NOT_FOUND_KEY", "EMPTY_KEY"]), Some(11));
+    assert_eq!(exists(&["--target-env", "tests/.test-env", "--any", "NOT_FOUND_KEY"]), Some(1));
+}
+
+#[test]
+fn test_exists_failed_by_unknown_argument() {
+    assert_eq!(exists(&["--target-env", "tests/.test-env", "--bogus", "EMPTY_KEY"]), Some(2));
+}
+
+#[test]
+fn test_exists_failed_by_not_found_env_path() {
+    assert_eq!(exists(&["--target-env", "tests/.not-found-env", "SOME_APP_KEY"]), Some(66));
+}
+
+#[test]
+fn test_exists_successful_without_warnings() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("exists")
+        .arg("--target-env")
+        .arg("tests/.broken-env")
+        .arg("GOOD_KEY")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    let stderr = String::from_utf8(output.stderr).expect("Failed to parse stderr as UTF-8");
+    assert!(!stderr.contains("Skip parse line"));
+}
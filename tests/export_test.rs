@@ -0,0 +1,116 @@
+use std::process::Command;
+
+#[test]
+fn test_export_successful_with_bash_eval() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("export")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg("--shell")
+        .arg("bash")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let exports = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    let output = Command::new("bash")
+        .arg("-c")
+        .arg("eval \"$1\"; printf '%s|' \"$SINGLE_QUOTED_KEY\" \"$ESCAPED_QUOTED_KEY\" \"$MULTILINE_KEY\"")
+        .arg("bash")
+        .arg(exports)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(
+        stdout,
+        "single $quoted|first\nsecond \"inner\" \\|-----BEGIN KEY-----\nfirst line\nsecond line\n-----END KEY-----|"
+    );
+}
+
+#[test]
+fn test_export_successful_with_fish_and_prefix() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("export")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg("--shell")
+        .arg("fish")
+        .arg("SOME_APP")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout, "set -gx SOME_APP_KEY 'app-key'\nset -gx SOME_APP_SECRET 'app-secret'\n");
+}
+
+#[test]
+fn test_export_successful_with_json_warning_for_skipped_key() {
+    let target_env_path = "tests/.test-env-export-json-warning";
+    std::fs::write(target_env_path, "app.name=envdb\nAPP_MODE=test\n").expect("Failed to write env file");
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("--output")
+        .arg("json")
+        .arg("export")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("--shell")
+        .arg("bash")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout, "export APP_MODE='test'\n");
+
+    let stderr = String::from_utf8(output.stderr).expect("Failed to parse stderr as UTF-8");
+    let warning: serde_json::Value =
+        serde_json::from_str(stderr.lines().last().unwrap()).expect("Failed to parse stderr as JSON");
+    assert_eq!(warning["warning"], "Skip key the shell cannot name: app.name");
+}
+
+#[test]
+fn test_render_exports_escapes_values() {
+    let env_pairs = [
+        envdb::EnvPair {
+            key: "QUOTES".to_string(),
+            value: "it's \"$HOME\"\n\\".to_string(),
+            exported: false,
+        },
+        envdb::EnvPair {
+            key: "app.name".to_string(),
+            value: "\u{2019}".to_string(),
+            exported: false,
+        },
+    ];
+
+    assert_eq!(
+        envdb::render_exports(envdb::Shell::Posix, &env_pairs),
+        "export QUOTES='it'\\''s \"$HOME\"\n\\'\n"
+    );
+    assert_eq!(
+        envdb::render_exports(envdb::Shell::Fish, &env_pairs),
+        "set -gx QUOTES 'it\\'s \"$HOME\"\n\\\\'\n"
+    );
+    assert_eq!(
+        envdb::render_exports(envdb::Shell::PowerShell, &env_pairs),
+        "$env:QUOTES = 'it''s \"$HOME\"\n\\'\n${env:app.name} = '\u{2019}\u{2019}'\n"
+    );
+    assert_eq!(
+        envdb::render_exports(envdb::Shell::Nushell, &env_pairs),
+        "load-env { \"QUOTES\": \"it's \\\"$HOME\\\"\\n\\\\\", \"app.name\": \"\u{2019}\" }\n"
+    );
+}
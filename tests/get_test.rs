@@ -30,7 +30,7 @@ fn test_get_successful_with_not_found() {
         .output()
         .expect("Failed to execute command");
 
-    assert!(!output.status.success());
+    assert_eq!(output.status.code(), Some(1));
 
     let stdout = String::from_utf8(output.stderr).expect("Failed to parse stdout as UTF-8");
     assert!(stdout.trim().ends_with("Not found key in env file: tests/.test-env"));
@@ -48,8 +48,540 @@ fn test_get_failed_by_not_found_env_path() {
         .output()
         .expect("Failed to execute command");
 
-    assert!(!output.status.success());
+    assert_eq!(output.status.code(), Some(66));
 
     let stdout = String::from_utf8(output.stderr).expect("Failed to parse stdout as UTF-8");
     assert!(stdout.trim().ends_with("Failed to open the file: tests/.not-found-env"));
 }
+
+#[test]
+fn test_get_successful_with_double_quoted() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg("DOUBLE_QUOTED_KEY")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout, "quoted value\n");
+}
+
+#[test]
+fn test_get_successful_with_single_quoted() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg("SINGLE_QUOTED_KEY")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout, "single $quoted\n");
+}
+
+#[test]
+fn test_get_successful_with_escaped() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg("ESCAPED_QUOTED_KEY")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout, "first\nsecond \"inner\" \\\n");
+}
+
+#[test]
+fn test_get_successful_with_multiline() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg("MULTILINE_KEY")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout, "-----BEGIN KEY-----\nfirst line\nsecond line\n-----END KEY-----\n");
+}
+
+#[test]
+fn test_get_successful_with_export_prefix() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg("EXPORTED_KEY")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout.trim(), "exported-value");
+}
+
+#[test]
+fn test_get_failed_by_unparsable_entry() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--target-env")
+        .arg("tests/.broken-env")
+        .arg("BROKEN_KEY")
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(65));
+
+    let stdout = String::from_utf8(output.stderr).expect("Failed to parse stdout as UTF-8");
+    assert!(stdout.trim().ends_with("Failed to parse line 2 in env file: tests/.broken-env"));
+}
+
+#[test]
+fn test_get_successful_with_expand() {
+    let cases = [
+        ("DATABASE_URL", "postgres://admin:p@ss$word@localhost/app"),
+        ("GREETING", "hello admin, it costs $5"),
+        ("DB_PORT", "5432"),
+    ];
+
+    for (key, value) in cases {
+        let output = Command::new("cargo")
+            .arg("run")
+            .arg("--")
+            .arg("get")
+            .arg("--target-env")
+            .arg("tests/.expand-env")
+            .arg("--expand")
+            .arg(key)
+            .output()
+            .expect("Failed to execute command");
+
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+        assert_eq!(stdout.trim(), value);
+    }
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--target-env")
+        .arg("tests/.expand-env")
+        .arg("DATABASE_URL")
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout.trim(), "postgres://${DB_USER}:${DB_PASS}@${DB_HOST}/app");
+}
+
+#[test]
+fn test_get_successful_with_expand_env() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--target-env")
+        .arg("tests/.expand-env")
+        .arg("--expand-env")
+        .arg("FROM_ENV")
+        .env("ENVDB_TEST_VAR", "from-process")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout.trim(), "from-process");
+}
+
+#[test]
+fn test_get_failed_by_expand() {
+    let cases = [
+        ("LOOP_A", 6, "Reference cycle between variables: LOOP_A -> LOOP_B -> LOOP_A"),
+        ("BROKEN_URL", 5, "Undefined variable NOT_DEFINED referenced by BROKEN_URL"),
+        ("FROM_ENV", 5, "Undefined variable ENVDB_TEST_VAR referenced by FROM_ENV"),
+    ];
+
+    for (key, code, message) in cases {
+        let output = Command::new("cargo")
+            .arg("run")
+            .arg("--")
+            .arg("get")
+            .arg("--target-env")
+            .arg("tests/.expand-env")
+            .arg("--expand")
+            .arg(key)
+            .env("ENVDB_TEST_VAR", "from-process")
+            .output()
+            .expect("Failed to execute command");
+
+        assert_eq!(output.status.code(), Some(code));
+
+        let stdout = String::from_utf8(output.stderr).expect("Failed to parse stdout as UTF-8");
+        assert!(stdout.trim().ends_with(message), "{}", stdout);
+    }
+}
+
+#[test]
+fn test_get_successful_with_spacing_and_inline_comment() {
+    let cases = [
+        ("INLINE_COMMENT_KEY", "spaced value"),
+        ("HASH_VALUE_KEY", "http://example.com/#anchor"),
+        ("QUOTED_COMMENT_KEY", "quoted # not comment"),
+    ];
+
+    for (key, value) in cases {
+        let output = Command::new("cargo")
+            .arg("run")
+            .arg("--")
+            .arg("get")
+            .arg("--target-env")
+            .arg("tests/.test-env")
+            .arg(key)
+            .output()
+            .expect("Failed to execute command");
+
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+        assert_eq!(stdout, format!("{}\n", value));
+    }
+}
+
+#[test]
+fn test_get_successful_with_duplicates() {
+    let cases = [("first", "first"), ("last", "second")];
+
+    for (policy, expected) in cases {
+        let output = Command::new("cargo")
+            .arg("run")
+            .arg("--")
+            .arg("get")
+            .arg("--target-env")
+            .arg("tests/.duplicate-env")
+            .arg("--duplicates")
+            .arg(policy)
+            .arg("APP_KEY")
+            .output()
+            .expect("Failed to execute command");
+
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+        assert_eq!(stdout.trim(), expected);
+    }
+}
+
+#[test]
+fn test_get_failed_by_duplicates() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--target-env")
+        .arg("tests/.duplicate-env")
+        .arg("--duplicates")
+        .arg("error")
+        .arg("APP_KEY")
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(3));
+
+    let stderr = String::from_utf8(output.stderr).expect("Failed to parse stderr as UTF-8");
+    assert!(stderr.trim().ends_with("Duplicate key APP_KEY on lines 1, 3"));
+}
+
+#[test]
+fn test_get_successful_with_crlf_and_bom() {
+    let cases = [("FIRST_KEY", "first"), ("QUOTED_KEY", "quoted value"), ("LAST_KEY", "last")];
+
+    for (key, expected) in cases {
+        let output = Command::new("cargo")
+            .arg("run")
+            .arg("--")
+            .arg("get")
+            .arg("--target-env")
+            .arg("tests/.crlf-env")
+            .arg(key)
+            .output()
+            .expect("Failed to execute command");
+
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+        assert_eq!(stdout, format!("{}\n", expected));
+    }
+}
+
+#[test]
+fn test_get_successful_with_undecodable_line() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--target-env")
+        .arg("tests/.latin1-env")
+        .arg("GOOD_KEY")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout.trim(), "good");
+}
+
+#[test]
+fn test_get_failed_by_undecodable_entry() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--target-env")
+        .arg("tests/.latin1-env")
+        .arg("LATIN1_KEY")
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(8));
+
+    let stderr = String::from_utf8(output.stderr).expect("Failed to parse stderr as UTF-8");
+    assert!(stderr.trim().ends_with("Failed to read the entry for LATIN1_KEY on line 2 as UTF-8"));
+}
+
+#[test]
+fn test_get_successful_with_raw() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--target-env")
+        .arg("tests/.latin1-env")
+        .arg("--raw")
+        .arg("LATIN1_KEY")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"caf\xe9\n");
+}
+
+#[test]
+fn test_get_successful_with_json() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("--output")
+        .arg("json")
+        .arg("get")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg("SOME_APP_KEY")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let entry: serde_json::Value = serde_json::from_slice(&output.stdout).expect("Failed to parse stdout as JSON");
+    assert_eq!(
+        entry,
+        serde_json::json!({ "key": "SOME_APP_KEY", "value": "app-key", "line": 3, "file": "tests/.test-env" })
+    );
+}
+
+#[test]
+fn test_get_failed_with_json() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--output")
+        .arg("json")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg("NOT_FOUND_KEY")
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(1));
+
+    let stderr = String::from_utf8(output.stderr).expect("Failed to parse stderr as UTF-8");
+    let error: serde_json::Value =
+        serde_json::from_str(stderr.trim().lines().last().unwrap_or_default()).expect("Failed to parse stderr as JSON");
+    assert_eq!(error["code"], 1);
+    assert_eq!(error["error"], "Not found key in env file: tests/.test-env");
+}
+
+#[test]
+fn test_get_successful_with_layers() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--target-env")
+        .arg("tests/.layer-env")
+        .arg("--target-env")
+        .arg("tests/.layer-env.production")
+        .arg("DATABASE_URL")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout, "postgres://db.internal/prod\n");
+}
+
+#[test]
+fn test_get_failed_by_missing_layer() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--target-env")
+        .arg("tests/.layer-env")
+        .arg("--target-env")
+        .arg("tests/.layer-env.missing")
+        .arg("DATABASE_URL")
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(66));
+}
+
+#[test]
+fn test_get_successful_with_mode_and_expand() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--target-env")
+        .arg("tests/.layer-env")
+        .arg("--mode")
+        .arg("production")
+        .arg("--expand")
+        .arg("APP_URL")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout, "https://envdb-local.example.com\n");
+}
+
+#[test]
+fn test_get_successful_with_explain() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--target-env")
+        .arg("tests/.layer-env")
+        .arg("--mode")
+        .arg("production")
+        .arg("--explain")
+        .arg("DATABASE_URL")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(
+        stdout,
+        "tests/.layer-env.local:1 DATABASE_URL=postgres://localhost/override\n\
+         tests/.layer-env.production:2 DATABASE_URL=postgres://db.internal/prod (shadowed)\n\
+         tests/.layer-env:3 DATABASE_URL=postgres://localhost/dev (shadowed)\n"
+    );
+}
+
+#[test]
+fn test_get_successful_with_json_warnings() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("--output")
+        .arg("json")
+        .arg("get")
+        .arg("--target-env")
+        .arg("tests/.broken-env")
+        .arg("NOT_FOUND_KEY")
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(1));
+
+    let stderr = String::from_utf8(output.stderr).expect("Failed to parse stderr as UTF-8");
+    let records: Vec<serde_json::Value> = stderr
+        .lines()
+        .skip_while(|line| !line.starts_with('{'))
+        .map(|line| serde_json::from_str(line).expect("Failed to parse stderr as JSON"))
+        .collect();
+    assert!(records
+        .iter()
+        .any(|record| record["warning"].as_str().is_some_and(|warning| warning.starts_with("Skip parse line: "))));
+    assert_eq!(records.last().unwrap()["code"], 1);
+}
+
+#[test]
+fn test_get_successful_after_unclosed_quote() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--target-env")
+        .arg("tests/.unclosed-env")
+        .arg("B")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout, "1\n");
+}
+
+#[test]
+fn test_get_error_variants() {
+    let err = envdb::get(std::path::Path::new("tests/.not-found-env"), "SOME_APP_KEY", &Default::default()).unwrap_err();
+    match err {
+        envdb::EnvDbError::Io { source, .. } => assert_eq!(source.kind(), std::io::ErrorKind::NotFound),
+        other => panic!("unexpected error: {:?}", other),
+    }
+
+    let err = envdb::get(std::path::Path::new("tests/.test-env"), "NOT_FOUND_KEY", &Default::default()).unwrap_err();
+    assert!(matches!(err, envdb::EnvDbError::NotFound { ref key, .. } if key == "NOT_FOUND_KEY"));
+
+    let err = envdb::get(std::path::Path::new("tests/.broken-env"), "BROKEN_KEY", &Default::default()).unwrap_err();
+    assert!(matches!(err, envdb::EnvDbError::Parse { line: 2, .. }));
+}
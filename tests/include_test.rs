@@ -0,0 +1,171 @@
+use std::process::Command;
+
+#[test]
+fn test_get_successful_with_include() {
+    for (key, value) in [("LOG_LEVEL", "info"), ("APP_NAME", "service")] {
+        let output = Command::new("cargo")
+            .arg("run")
+            .arg("--")
+            .arg("get")
+            .arg("--target-env")
+            .arg("tests/.include-env")
+            .arg(key)
+            .output()
+            .expect("Failed to execute command");
+
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+        assert_eq!(stdout, format!("{}\n", value));
+    }
+}
+
+#[test]
+fn test_get_successful_with_include_and_expand() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--target-env")
+        .arg("tests/.include-env")
+        .arg("--expand")
+        .arg("DATABASE_URL")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout, "postgres://db.internal/service\n");
+}
+
+#[test]
+fn test_list_successful_with_include() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("--output")
+        .arg("json")
+        .arg("list")
+        .arg("--target-env")
+        .arg("tests/.include-env")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let entries: serde_json::Value = serde_json::from_slice(&output.stdout).expect("Failed to parse stdout as JSON");
+    let sources: Vec<(&str, &str)> = entries
+        .as_array()
+        .expect("Expected a JSON array")
+        .iter()
+        .map(|entry| (entry["key"].as_str().unwrap(), entry["file"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        sources,
+        [
+            ("DB_HOST", "tests/.include-common-env"),
+            ("LOG_LEVEL", "tests/.include-common-env"),
+            ("APP_NAME", "tests/.include-env"),
+            ("DATABASE_URL", "tests/.include-env"),
+        ]
+    );
+}
+
+#[test]
+fn test_get_failed_by_include_cycle() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--target-env")
+        .arg("tests/.include-cycle-env")
+        .arg("FIRST")
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(7));
+
+    let stderr = String::from_utf8(output.stderr).expect("Failed to parse stderr as UTF-8");
+    assert!(stderr.contains(
+        "Include cycle between files: tests/.include-cycle-env -> tests/.include-cycle-other-env -> tests/.include-cycle-env"
+    ));
+}
+
+#[test]
+fn test_put_failed_by_included_key() {
+    let origin_env_path = "tests/.include-env";
+    let target_env_path = "tests/.test-env-put-failed-by-included-key";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("APP_NAME=renamed")
+        .arg("LOG_LEVEL=debug")
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(4));
+
+    let stderr = String::from_utf8(output.stderr).expect("Failed to parse stderr as UTF-8");
+    assert!(stderr.contains("LOG_LEVEL is set in the included file tests/.include-common-env; edit that file instead"));
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert_eq!(content, std::fs::read_to_string(origin_env_path).expect("Failed to read env file"));
+}
+
+#[test]
+fn test_delete_failed_by_included_key() {
+    let origin_env_path = "tests/.include-env";
+    let target_env_path = "tests/.test-env-delete-failed-by-included-key";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("delete")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("DB_HOST")
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(4));
+}
+
+#[test]
+fn test_apply_failed_by_included_key() {
+    let origin_env_path = "tests/.include-env";
+    let target_env_path = "tests/.test-env-apply-failed-by-included-key";
+    let script_path = "tests/.test-env-apply-failed-by-included-key-script";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    for script in ["delete DB_HOST\n", "set LOG_LEVEL=debug\n", "rename LOG_LEVEL LEVEL\n"] {
+        std::fs::write(script_path, script).expect("Failed to write script");
+
+        let output = Command::new("cargo")
+            .arg("run")
+            .arg("--")
+            .arg("apply")
+            .arg("--target-env")
+            .arg(target_env_path)
+            .arg(script_path)
+            .output()
+            .expect("Failed to execute command");
+
+        assert_eq!(output.status.code(), Some(4), "script: {}", script);
+    }
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert_eq!(content, std::fs::read_to_string(origin_env_path).expect("Failed to read env file"));
+}
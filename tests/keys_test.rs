@@ -0,0 +1,39 @@
+use std::process::Command;
+
+#[test]
+fn test_keys_successful() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("keys")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg("SOME_")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout, "SOME_APP_KEY\nSOME_APP_SECRET\n");
+}
+
+#[test]
+fn test_keys_successful_with_sort_and_json() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("keys")
+        .arg("--target-env")
+        .arg("tests/.duplicate-env")
+        .arg("--sort")
+        .arg("--output")
+        .arg("json")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let keys: serde_json::Value = serde_json::from_slice(&output.stdout).expect("Failed to parse stdout as JSON");
+    assert_eq!(keys, serde_json::json!(["APP_KEY", "OTHER_KEY"]));
+}
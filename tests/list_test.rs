@@ -0,0 +1,131 @@
+use std::process::Command;
+
+#[test]
+fn test_list_successful() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("list")
+        .arg("--target-env")
+        .arg("tests/.duplicate-env")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout, "OTHER_KEY=other\nAPP_KEY=second\n");
+}
+
+#[test]
+fn test_list_successful_with_json() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("list")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg("--output")
+        .arg("json")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let entries: serde_json::Value = serde_json::from_slice(&output.stdout).expect("Failed to parse stdout as JSON");
+    let multiline = entries
+        .as_array()
+        .expect("Expected a JSON array")
+        .iter()
+        .find(|entry| entry["key"] == "MULTILINE_KEY")
+        .expect("Expected MULTILINE_KEY to be listed");
+
+    assert_eq!(multiline["value"], "-----BEGIN KEY-----\nfirst line\nsecond line\n-----END KEY-----");
+    assert_eq!(multiline["line"], 9);
+    assert_eq!(multiline["file"], "tests/.test-env");
+}
+
+#[test]
+fn test_list_successful_with_sort_and_prefix() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("list")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg("--sort")
+        .arg("S")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(
+        stdout,
+        "SINGLE_QUOTED_KEY='single $quoted'\nSOME_APP_KEY=app-key\nSOME_APP_SECRET=app-secret\nSSH_AUTH_SOCK_DOCKER_HOST=/some/host-socket.sock\n"
+    );
+}
+
+#[test]
+fn test_list_successful_with_empty_file() {
+    let target_env_path = "tests/.test-env-list-successful-with-empty-file";
+    std::fs::write(target_env_path, "").expect("Failed to write env file");
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("list")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_list_successful_with_mode() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("list")
+        .arg("--target-env")
+        .arg("tests/.layer-env")
+        .arg("--mode")
+        .arg("production")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(
+        stdout,
+        "APP_URL='https://$APP_NAME.example.com'\nAPP_MODE=production\nDATABASE_URL=postgres://localhost/override\nAPP_NAME=envdb-local\n"
+    );
+}
+
+#[test]
+fn test_load_layers_records_source_files() {
+    let paths = [
+        std::path::PathBuf::from("tests/.layer-env"),
+        std::path::PathBuf::from("tests/.layer-env.production"),
+    ];
+    let entries = envdb::load_layers(&paths, &Default::default()).unwrap();
+
+    let sources: Vec<(&str, &str)> = entries
+        .iter()
+        .map(|entry| (entry.env_pair.key.as_str(), entry.path.to_str().unwrap()))
+        .collect();
+    assert_eq!(
+        sources,
+        [
+            ("APP_NAME", "tests/.layer-env"),
+            ("APP_URL", "tests/.layer-env"),
+            ("APP_MODE", "tests/.layer-env.production"),
+            ("DATABASE_URL", "tests/.layer-env.production"),
+        ]
+    );
+}
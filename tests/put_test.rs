@@ -92,3 +92,719 @@ fn test_put_failed_by_not_found_env_path() {
     let stdout = String::from_utf8(output.stderr).expect("Failed to parse stdout as UTF-8");
     assert!(stdout.trim().ends_with("Failed to open the file: tests/.not-found-env"));
 }
+
+#[test]
+fn test_put_successful_with_quoting() {
+    let origin_env_path = "tests/.test-env";
+    let target_env_path = "tests/.test-env-put-successful-with-quoting";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    let cases = [
+        ("SPACED_KEY", "has space # not comment", "SPACED_KEY='has space # not comment'"),
+        ("QUOTE_KEY", "it's \"here\"\n$HOME", "QUOTE_KEY=\"it's \\\"here\\\"\\n\\$HOME\""),
+    ];
+
+    for (key, value, line) in cases {
+        let output = Command::new("cargo")
+            .arg("run")
+            .arg("--")
+            .arg("put")
+            .arg("--target-env")
+            .arg(target_env_path)
+            .arg(key)
+            .arg(value)
+            .output()
+            .expect("Failed to execute command");
+
+        assert!(output.status.success());
+
+        let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+        assert!(content.lines().any(|l| l == line), "{} not found in {}", line, content);
+
+        let output = Command::new("cargo")
+            .arg("run")
+            .arg("--")
+            .arg("get")
+            .arg("--target-env")
+            .arg(target_env_path)
+            .arg(key)
+            .output()
+            .expect("Failed to execute command");
+
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+        assert_eq!(stdout, format!("{}\n", value));
+    }
+}
+
+#[test]
+fn test_put_successful_with_multiline() {
+    let origin_env_path = "tests/.test-env";
+    let target_env_path = "tests/.test-env-put-successful-with-multiline";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("MULTILINE_KEY")
+        .arg("replaced")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert!(content.contains("MULTILINE_KEY=replaced\n"));
+    assert!(!content.contains("first line"));
+    assert!(!content.contains("-----END KEY-----"));
+}
+
+#[test]
+fn test_put_successful_with_export_prefix() {
+    let origin_env_path = "tests/.test-env";
+    let target_env_path = "tests/.test-env-put-successful-with-export-prefix";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("EXPORTED_KEY")
+        .arg("new-exported-value")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("--export")
+        .arg("NEW_EXPORTED_KEY")
+        .arg("new-value")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert!(content.contains("\nexport EXPORTED_KEY=new-exported-value\n"));
+    assert!(!content.contains("\nEXPORTED_KEY="));
+    assert!(content.ends_with("\nexport NEW_EXPORTED_KEY=new-value\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_put_successful_with_permissions_preserved() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let origin_env_path = "tests/.test-env";
+    let target_env_path = "tests/.test-env-put-successful-with-permissions-preserved";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+    std::fs::set_permissions(target_env_path, std::fs::Permissions::from_mode(0o600))
+        .expect("Failed to set permissions");
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("SOME_APP_KEY")
+        .arg("new-app-key")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let metadata = std::fs::metadata(target_env_path).expect("Failed to read metadata");
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_put_successful_with_symlink() {
+    let origin_env_path = "tests/.test-env";
+    let real_env_path = "tests/.test-env-put-successful-with-symlink-real";
+    let link_env_path = "tests/.test-env-put-successful-with-symlink-link";
+    if let Err(err) = std::fs::copy(origin_env_path, real_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+    let _ = std::fs::remove_file(link_env_path);
+    std::os::unix::fs::symlink(".test-env-put-successful-with-symlink-real", link_env_path)
+        .expect("Failed to create symlink");
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg(link_env_path)
+        .arg("--follow-symlinks")
+        .arg("SOME_APP_KEY")
+        .arg("through-link")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let metadata = std::fs::symlink_metadata(link_env_path).expect("Failed to read metadata");
+    assert!(metadata.file_type().is_symlink());
+    let content = std::fs::read_to_string(real_env_path).expect("Failed to read env file");
+    assert!(content.contains("SOME_APP_KEY=through-link\n"));
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg(link_env_path)
+        .arg("SOME_APP_KEY")
+        .arg("replaced-link")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let metadata = std::fs::symlink_metadata(link_env_path).expect("Failed to read metadata");
+    assert!(metadata.file_type().is_file());
+    let content = std::fs::read_to_string(real_env_path).expect("Failed to read env file");
+    assert!(content.contains("SOME_APP_KEY=through-link\n"));
+}
+
+#[test]
+fn test_put_successful_with_concurrent_writers() {
+    let origin_env_path = "tests/.test-env";
+    let target_env_path = "tests/.test-env-put-successful-with-concurrent-writers";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    let children: Vec<_> = (0..8)
+        .map(|i| {
+            Command::new("cargo")
+                .arg("run")
+                .arg("--")
+                .arg("put")
+                .arg("--target-env")
+                .arg(target_env_path)
+                .arg(format!("CONCURRENT_KEY_{}", i))
+                .arg(format!("value-{}", i))
+                .spawn()
+                .expect("Failed to execute command")
+        })
+        .collect();
+
+    for mut child in children {
+        assert!(child.wait().expect("Failed to wait command").success());
+    }
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    for i in 0..8 {
+        assert!(content.contains(&format!("CONCURRENT_KEY_{}=value-{}\n", i, i)), "{}", content);
+    }
+}
+
+#[test]
+fn test_put_failed_by_lock_timeout() {
+    let origin_env_path = "tests/.test-env";
+    let target_env_path = "tests/.test-env-put-failed-by-lock-timeout";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    let locked_file = std::fs::File::open(target_env_path).expect("Failed to open env file");
+    locked_file.lock_shared().expect("Failed to lock env file");
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("--lock-timeout")
+        .arg("0.1")
+        .arg("SOME_APP_KEY")
+        .arg("new-app-key")
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(75));
+
+    let stdout = String::from_utf8(output.stderr).expect("Failed to parse stdout as UTF-8");
+    assert!(stdout.trim().ends_with(
+        "Failed to lock the file within 0.1s, another process is using it: tests/.test-env-put-failed-by-lock-timeout"
+    ));
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert!(content.contains("SOME_APP_KEY=app-key\n"));
+}
+
+#[test]
+fn test_put_successful_with_many_assignments() {
+    let origin_env_path = "tests/.test-env";
+    let target_env_path = "tests/.test-env-put-successful-with-many-assignments";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("SOME_APP_KEY=new-app-key")
+        .arg("NEW_APP_KEY=a=b")
+        .arg("SOME_APP_SECRET=new-secret")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout, "added NEW_APP_KEY\nreplaced SOME_APP_KEY\nreplaced SOME_APP_SECRET\n");
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert!(content.starts_with("# This is comment\n\nSOME_APP_KEY=new-app-key\nSOME_APP_SECRET=new-secret\n"));
+    assert!(content.ends_with("\nNEW_APP_KEY=a=b\n"));
+}
+
+#[test]
+fn test_put_successful_with_from_file_and_stdin() {
+    use std::io::Write;
+
+    let origin_env_path = "tests/.test-env";
+    let target_env_path = "tests/.test-env-put-successful-with-from-file-and-stdin";
+    let source_env_path = "tests/.test-env-put-successful-with-from-file-and-stdin-source";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+    std::fs::write(source_env_path, "# source\nSOME_APP_KEY=from-file\nFILE_KEY='from file'\n")
+        .expect("Failed to write source env file");
+
+    let mut child = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("--from-file")
+        .arg(source_env_path)
+        .arg("--stdin")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to execute command");
+    child
+        .stdin
+        .take()
+        .expect("Failed to open stdin")
+        .write_all(b"STDIN_KEY=\"from\\nstdin\"\nFILE_KEY=overridden\n")
+        .expect("Failed to write stdin");
+    let output = child.wait_with_output().expect("Failed to wait command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout, "added FILE_KEY\nadded STDIN_KEY\nreplaced SOME_APP_KEY\n");
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert!(content.contains("\nSOME_APP_KEY=from-file\n"));
+    assert!(content.ends_with("\nFILE_KEY=overridden\nSTDIN_KEY=\"from\\nstdin\"\n"));
+}
+
+#[test]
+fn test_put_failed_by_invalid_assignment() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg("SOME_APP_KEY=new-app-key")
+        .arg("MISSING_EQUAL")
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(64));
+
+    let stdout = String::from_utf8(output.stderr).expect("Failed to parse stdout as UTF-8");
+    assert!(stdout.trim().ends_with("Invalid assignment, expected KEY=VALUE: MISSING_EQUAL"));
+}
+
+#[test]
+fn test_put_successful_with_spacing_and_inline_comment() {
+    let origin_env_path = "tests/.test-env";
+    let target_env_path = "tests/.test-env-put-successful-with-spacing-and-inline-comment";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("INLINE_COMMENT_KEY=new value")
+        .arg("QUOTED_COMMENT_KEY=plain")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout, "replaced INLINE_COMMENT_KEY\nreplaced QUOTED_COMMENT_KEY\n");
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert!(content.contains("\nINLINE_COMMENT_KEY = 'new value'  # prod value\n"));
+    assert!(content.contains("\nQUOTED_COMMENT_KEY = plain # comment\n"));
+}
+
+#[test]
+fn test_put_successful_with_duplicates() {
+    let origin_env_path = "tests/.duplicate-env";
+    let target_env_path = "tests/.test-env-put-successful-with-duplicates";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("APP_KEY=updated")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert_eq!(content, "APP_KEY=first\nOTHER_KEY=other\nAPP_KEY=updated\n");
+}
+
+#[test]
+fn test_put_failed_by_invalid_key() {
+    let origin_env_path = "tests/.test-env";
+    let target_env_path = "tests/.test-env-put-failed-by-invalid-key";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("GOOD_KEY=good")
+        .arg("1BAD_KEY=bad")
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(12));
+
+    let stderr = String::from_utf8(output.stderr).expect("Failed to parse stderr as UTF-8");
+    assert!(stderr.trim().ends_with("Invalid key: \"1BAD_KEY\""));
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    let origin_content = std::fs::read_to_string(origin_env_path).expect("Failed to read env file");
+    assert_eq!(content, origin_content);
+}
+
+#[test]
+fn test_put_successful_with_allow_any_key() {
+    let origin_env_path = "tests/.test-env";
+    let target_env_path = "tests/.test-env-put-successful-with-allow-any-key";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("--allow-any-key")
+        .arg("app.name=envdb")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("app.name")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout.trim(), "envdb");
+}
+
+#[test]
+fn test_put_successful_with_crlf_and_bom() {
+    let origin_env_path = "tests/.crlf-env";
+    let target_env_path = "tests/.test-env-put-successful-with-crlf-and-bom";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("FIRST_KEY=updated")
+        .arg("NEW_KEY=new")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert_eq!(
+        content,
+        "\u{feff}FIRST_KEY=updated\r\nQUOTED_KEY=\"quoted value\"\r\nLAST_KEY=last\r\nNEW_KEY=new"
+    );
+}
+
+#[test]
+fn test_put_successful_with_undecodable_line() {
+    let origin_env_path = "tests/.latin1-env";
+    let target_env_path = "tests/.test-env-put-successful-with-undecodable-line";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("GOOD_KEY=better")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let content = std::fs::read(target_env_path).expect("Failed to read env file");
+    assert_eq!(content, b"GOOD_KEY=better\nLATIN1_KEY=\"caf\xe9\"\n");
+}
+
+fn copy_layers(target_env_path: &str) {
+    for suffix in ["", ".production", ".local"] {
+        let origin_env_path = format!("tests/.layer-env{}", suffix);
+        if let Err(err) = std::fs::copy(origin_env_path, format!("{}{}", target_env_path, suffix)) {
+            panic!("Fail to copy origin env to test own env: {}", err);
+        }
+    }
+}
+
+#[test]
+fn test_put_successful_with_layers() {
+    let target_env_path = "tests/.test-env-put-successful-with-layers";
+    copy_layers(target_env_path);
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("--mode")
+        .arg("production")
+        .arg("APP_MODE=staging")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let local = std::fs::read_to_string(format!("{}.local", target_env_path)).expect("Failed to read env file");
+    assert_eq!(local, "DATABASE_URL=postgres://localhost/override\nAPP_NAME=envdb-local\nAPP_MODE=staging\n");
+
+    let production = format!("{}.production", target_env_path);
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("--mode")
+        .arg("production")
+        .arg("--write-layer")
+        .arg(&production)
+        .arg("APP_MODE=canary")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let production = std::fs::read_to_string(production).expect("Failed to read env file");
+    assert_eq!(production, "APP_MODE=canary\nDATABASE_URL=postgres://db.internal/prod\n");
+}
+
+#[test]
+fn test_put_failed_by_unknown_write_layer() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg("tests/.layer-env")
+        .arg("--write-layer")
+        .arg("tests/.test-env")
+        .arg("APP_MODE=staging")
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(64));
+
+    let stderr = String::from_utf8(output.stderr).expect("Failed to parse stderr as UTF-8");
+    assert!(stderr.contains("Not a layer of this command: tests/.test-env"));
+}
+
+#[test]
+fn test_put_many_rejects_invalid_keys() {
+    let options = envdb::PutOptions {
+        allow_any_key: true,
+        ..Default::default()
+    };
+    let env_pair = envdb::EnvPair {
+        key: "BAD=KEY".to_string(),
+        value: "value".to_string(),
+        exported: false,
+    };
+
+    let err = envdb::put_many(std::path::Path::new("tests/.test-env"), &[env_pair], &options).unwrap_err();
+    assert!(matches!(err, envdb::EnvDbError::InvalidKey(ref key) if key == "BAD=KEY"));
+}
+
+#[test]
+fn test_put_successful_with_write_layer_spelled_differently() {
+    let target_env_path = "tests/.test-env-put-successful-with-write-layer-spelled-differently";
+    copy_layers(target_env_path);
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("--mode")
+        .arg("production")
+        .arg("--write-layer")
+        .arg(format!("./{}.production", target_env_path))
+        .arg("APP_MODE=canary")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let production = std::fs::read_to_string(format!("{}.production", target_env_path)).expect("Failed to read env file");
+    assert_eq!(production, "APP_MODE=canary\nDATABASE_URL=postgres://db.internal/prod\n");
+}
+
+#[test]
+fn test_put_successful_with_backticks_and_source() {
+    let origin_env_path = "tests/.test-env";
+    let target_env_path = "tests/.test-env-put-successful-with-backticks-and-source";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("BACKTICK_KEY")
+        .arg("it's `id`")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert!(content.contains("\nBACKTICK_KEY=\"it's \\`id\\`\"\n"));
+
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg("set -a; . \"./$1\"; printf '%s' \"$BACKTICK_KEY\"")
+        .arg("sh")
+        .arg(target_env_path)
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout, "it's `id`");
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("get")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("BACKTICK_KEY")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout, "it's `id`\n");
+}
+
+#[test]
+fn test_put_successful_after_unclosed_quote() {
+    let origin_env_path = "tests/.unclosed-env";
+    let target_env_path = "tests/.test-env-put-successful-after-unclosed-quote";
+    if let Err(err) = std::fs::copy(origin_env_path, target_env_path) {
+        panic!("Fail to copy origin env to test own env: {}", err);
+    }
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("put")
+        .arg("--target-env")
+        .arg(target_env_path)
+        .arg("B=9")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let content = std::fs::read_to_string(target_env_path).expect("Failed to read env file");
+    assert_eq!(content, "A=\"oops\nB=9\nC=2\n");
+}
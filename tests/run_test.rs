@@ -0,0 +1,110 @@
+use std::process::Command;
+
+#[test]
+fn test_run_successful() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("run")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg("--")
+        .arg("sh")
+        .arg("-c")
+        .arg("printf '%s|%s' \"$INLINE_COMMENT_KEY\" \"$QUOTED_COMMENT_KEY\"")
+        .env("INLINE_COMMENT_KEY", "inherited")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout, "spaced value|quoted # not comment");
+}
+
+#[test]
+fn test_run_successful_with_clean() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("run")
+        .arg("--clean")
+        .arg("--target-env")
+        .arg("tests/.layer-env")
+        .arg("--mode")
+        .arg("production")
+        .arg("--")
+        .arg("/usr/bin/env")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    let mut variables: Vec<&str> = stdout.lines().collect();
+    variables.sort();
+    assert_eq!(
+        variables,
+        [
+            "APP_MODE=production",
+            "APP_NAME=envdb-local",
+            "APP_URL=https://$APP_NAME.example.com",
+            "DATABASE_URL=postgres://localhost/override",
+        ]
+    );
+}
+
+#[test]
+fn test_run_propagates_exit_code() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("run")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg("--")
+        .arg("sh")
+        .arg("-c")
+        .arg("exit 3")
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_run_propagates_signal() {
+    use std::os::unix::process::ExitStatusExt;
+
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("run")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg("--")
+        .arg("sh")
+        .arg("-c")
+        .arg("kill -TERM $$")
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.signal(), Some(15));
+}
+
+#[test]
+fn test_run_failed_by_not_found_command() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("run")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg("--")
+        .arg("envdb-not-found-command")
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(127));
+}
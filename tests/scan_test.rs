@@ -50,3 +50,149 @@ fn test_scan_failed_by_not_found_env_path() {
     let stdout = String::from_utf8(output.stderr).expect("Failed to parse stdout as UTF-8");
     assert!(stdout.trim().ends_with("Failed to open the file: tests/.not-found-env"));
 }
+
+#[test]
+fn test_scan_successful_with_expand() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("scan")
+        .arg("--target-env")
+        .arg("tests/.expand-env")
+        .arg("--expand")
+        .arg("DB_")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout.trim(), "DB_USER=admin\nDB_PASS='p@ss$word'\nDB_HOST=localhost\nDB_PORT=5432");
+}
+
+#[test]
+fn test_scan_successful_with_include_commented() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("scan")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg("--include-commented")
+        .arg("SOME_")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout.trim(), "SOME_APP_KEY=app-key\nSOME_APP_SECRET=app-secret\n# SOME_APP_DEBUG=true");
+}
+
+#[test]
+fn test_scan_successful_with_jsonl() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("scan")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg("--output")
+        .arg("jsonl")
+        .arg("SOME_")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    let keys: Vec<String> = stdout
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).expect("Failed to parse line as JSON"))
+        .map(|entry| entry["key"].as_str().unwrap_or_default().to_string())
+        .collect();
+    assert_eq!(keys, ["SOME_APP_KEY", "SOME_APP_SECRET"]);
+}
+
+#[test]
+fn test_scan_successful_with_glob_and_ignore_case() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("scan")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg("--glob")
+        .arg("some_*_secret")
+        .arg("--ignore-case")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout.trim(), "SOME_APP_SECRET=app-secret");
+}
+
+#[test]
+fn test_scan_successful_with_regex_suffix_and_value_match() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("scan")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg("--regex")
+        .arg("^(SOME|SSH)_")
+        .arg("--suffix")
+        .arg("_HOST")
+        .arg("--value-match")
+        .arg("\\.sock$")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout.trim(), "SSH_AUTH_SOCK_DOCKER_HOST=/some/host-socket.sock");
+}
+
+#[test]
+fn test_scan_failed_by_invalid_regex() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("scan")
+        .arg("--target-env")
+        .arg("tests/.test-env")
+        .arg("--regex")
+        .arg("(")
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(64));
+
+    let stderr = String::from_utf8(output.stderr).expect("Failed to parse stderr as UTF-8");
+    assert!(stderr.contains("Invalid pattern \"(\""));
+}
+
+#[test]
+fn test_scan_successful_with_include_commented_and_mode() {
+    let output = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("scan")
+        .arg("--target-env")
+        .arg("tests/.commented-layer-env")
+        .arg("--mode")
+        .arg("production")
+        .arg("--include-commented")
+        .arg("DEBUG")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("Failed to parse stdout as UTF-8");
+    assert_eq!(stdout, "DEBUG=off\n# DEBUG=true\n# DEBUG=verbose\n");
+}